@@ -3,9 +3,10 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures::{Stream, StreamExt};
+use futures::{Future, FutureExt, Stream, StreamExt};
 use wasmtime::component::{
-    Access, Destination, HasData, Lift, Lower, StreamProducer, StreamReader, StreamResult,
+    Access, Destination, FutureProducer, HasData, Lift, Lower, StreamProducer, StreamReader,
+    StreamResult,
 };
 use wasmtime::StoreContextMut;
 
@@ -80,6 +81,35 @@ where
     }
 }
 
+/// Adapts a [`Future`] into a wasmtime [`FutureProducer`], mapping its output
+/// with access to the store data `D` before lowering (e.g. to push the value
+/// into the resource table).
+pub struct FuturePipeMap<Fut, F>(pub Fut, pub F);
+
+impl<D, In, Out, Fut, F> FutureProducer<D> for FuturePipeMap<Fut, F>
+where
+    Out: Lower + Send + Sync + 'static,
+    Fut: Future<Output = In> + Send + Unpin + 'static,
+    F: FnMut(&mut D, In) -> wasmtime::Result<Out> + Send + Unpin + 'static,
+{
+    type Item = Out;
+
+    fn poll_produce(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut store: StoreContextMut<D>,
+        finish: bool,
+    ) -> Poll<wasmtime::Result<Option<Self::Item>>> {
+        // `Fut: Unpin` and `F: Unpin`, so `Self: Unpin` and we can project freely.
+        let this = self.get_mut();
+        match this.0.poll_unpin(cx) {
+            Poll::Pending if finish => Poll::Ready(Ok(None)),
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(value) => Poll::Ready((this.1)(store.data_mut(), value).map(Some)),
+        }
+    }
+}
+
 /// Turns a broadcast receiver into a component-model stream. Needs the store
 /// (via `access`) because `StreamReader::new` registers the producer with it.
 pub fn channel_to_stream<T, D, A>(
@@ -0,0 +1,583 @@
+//! Surfaces that don't need a display, for running guests in CI or tests.
+//!
+//! [`HeadlessSpawner`] stands in for an event loop: it runs main thread closures inline, and creates surfaces backed by a [`HeadlessWindow`].
+//! Frames are driven by the embedder, either on demand with [`HeadlessSpawner::animation_frame`], or with a virtual clock through [`HeadlessSpawner::advance`].
+//!
+//! Contexts on a headless surface present into memory instead of to a window, see [`HeadlessWindow::latest_frame`].
+//! WebGPU frames stay on the GPU until they're asked for, so guests aren't slowed down by frames nobody looks at.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+
+use crate::surface::{
+    CursorGrab, CursorIcon, FrameSink, GfxWindow, MainThreadSpawner, PresentedFrame, ResizeEvent,
+    Surface, SurfaceDesc, WeakSurface, WindowSize,
+};
+
+// same as winit's default window size.
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+// same as the winit event loop's frame interval.
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A frame presented to a [`HeadlessWindow`], as read back by [`HeadlessWindow::latest_frame`].
+#[derive(Clone, Debug)]
+pub enum HeadlessFrame {
+    /// Presented by a `surface-frame-buffer` context. Each pixel is a `0RGB` `u32`, same as softbuffer.
+    FrameBuffer {
+        width: u32,
+        height: u32,
+        pixels: Vec<u32>,
+    },
+    /// Presented by a `surface-webgpu` context.
+    #[cfg(feature = "surface-webgpu")]
+    Webgpu(wasi_webgpu_wasmtime::CanvasFrame),
+}
+
+/// Window management state of a [`HeadlessWindow`], as last set by the guest.
+///
+/// Nothing acts on it, it's recorded so that tests can check what the guest asked for.
+#[derive(Clone, Debug)]
+pub struct HeadlessWindowAttributes {
+    pub title: String,
+    pub fullscreen: bool,
+    pub visible: bool,
+    pub resizable: bool,
+    pub decorations: bool,
+    pub min_size: Option<WindowSize>,
+    pub max_size: Option<WindowSize>,
+    pub cursor_visible: bool,
+    pub cursor_grab: CursorGrab,
+    pub cursor_icon: CursorIcon,
+}
+
+// same as winit's window defaults.
+impl Default for HeadlessWindowAttributes {
+    fn default() -> Self {
+        Self {
+            title: "winit window".to_string(),
+            fullscreen: false,
+            visible: true,
+            resizable: true,
+            decorations: true,
+            min_size: None,
+            max_size: None,
+            cursor_visible: true,
+            cursor_grab: CursorGrab::None,
+            cursor_icon: CursorIcon::Default,
+        }
+    }
+}
+
+/// In-memory window with a settable size.
+#[derive(Debug)]
+pub struct HeadlessWindow {
+    size: Mutex<(u32, u32)>,
+    // set when the size changed, until the resize event is sent by the spawner.
+    resized: Mutex<bool>,
+    attributes: Mutex<HeadlessWindowAttributes>,
+    latest_frame: Mutex<Option<PresentedFrame>>,
+}
+
+impl HeadlessWindow {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: Mutex::new((width, height)),
+            resized: Mutex::new(false),
+            attributes: Mutex::new(HeadlessWindowAttributes::default()),
+            latest_frame: Mutex::new(None),
+        }
+    }
+
+    /// Create a window the way a guest described it.
+    pub fn from_desc(desc: &SurfaceDesc) -> Self {
+        let window = Self::new(
+            desc.width.unwrap_or(DEFAULT_SIZE.0),
+            desc.height.unwrap_or(DEFAULT_SIZE.1),
+        );
+        {
+            let mut attributes = window.attributes.lock().unwrap();
+            if let Some(title) = &desc.title {
+                attributes.title = title.clone();
+            }
+            attributes.resizable = desc.resizable.unwrap_or(attributes.resizable);
+            attributes.decorations = desc.decorations.unwrap_or(attributes.decorations);
+            attributes.fullscreen = desc.fullscreen.unwrap_or(attributes.fullscreen);
+            attributes.visible = desc.visible.unwrap_or(attributes.visible);
+        }
+        window
+    }
+
+    /// Window management state, as last set by the guest.
+    pub fn attributes(&self) -> HeadlessWindowAttributes {
+        self.attributes.lock().unwrap().clone()
+    }
+
+    /// Resize the window. The surface gets a resize event on the next animation frame, like with a real event loop.
+    pub fn set_size(&self, width: u32, height: u32) {
+        let mut size = self.size.lock().unwrap();
+        if *size != (width, height) {
+            *size = (width, height);
+            *self.resized.lock().unwrap() = true;
+        }
+    }
+
+    /// The last frame presented by a context on this window, if any.
+    ///
+    /// Frames presented by a `surface-webgpu` context are read back from the GPU on every call, which blocks until the GPU is done with the frame.
+    /// `None` if reading it back fails.
+    pub fn latest_frame(&self) -> Option<HeadlessFrame> {
+        let frame = self.latest_frame.lock().unwrap().clone()?;
+        match frame {
+            PresentedFrame::FrameBuffer {
+                width,
+                height,
+                pixels,
+            } => Some(HeadlessFrame::FrameBuffer {
+                width,
+                height,
+                pixels,
+            }),
+            #[cfg(feature = "surface-webgpu")]
+            PresentedFrame::Webgpu(frame) => match frame.read() {
+                Ok(frame) => Some(HeadlessFrame::Webgpu(frame)),
+                Err(e) => {
+                    log::warn!("failed to read back headless frame: {e:#}");
+                    None
+                }
+            },
+        }
+    }
+
+    pub(crate) fn take_resized(&self) -> bool {
+        std::mem::take(&mut *self.resized.lock().unwrap())
+    }
+}
+
+// There is no display to hand out handles for, contexts check `GfxWindow::frame_sink` instead.
+impl HasDisplayHandle for HeadlessWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+impl HasWindowHandle for HeadlessWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl GfxWindow for HeadlessWindow {
+    fn height(&self) -> u32 {
+        self.size.lock().unwrap().1
+    }
+
+    fn width(&self) -> u32 {
+        self.size.lock().unwrap().0
+    }
+
+    fn request_set_size(&self, width: Option<u32>, height: Option<u32>) {
+        self.set_size(
+            width.unwrap_or(self.width()),
+            height.unwrap_or(self.height()),
+        );
+    }
+
+    fn set_title(&self, title: &str) {
+        self.attributes.lock().unwrap().title = title.to_string();
+    }
+
+    fn fullscreen(&self) -> bool {
+        self.attributes.lock().unwrap().fullscreen
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        self.attributes.lock().unwrap().fullscreen = fullscreen;
+    }
+
+    fn set_visible(&self, visible: bool) {
+        self.attributes.lock().unwrap().visible = visible;
+    }
+
+    fn set_resizable(&self, resizable: bool) {
+        self.attributes.lock().unwrap().resizable = resizable;
+    }
+
+    fn set_decorations(&self, decorations: bool) {
+        self.attributes.lock().unwrap().decorations = decorations;
+    }
+
+    fn set_min_size(&self, size: Option<WindowSize>) {
+        self.attributes.lock().unwrap().min_size = size;
+    }
+
+    fn set_max_size(&self, size: Option<WindowSize>) {
+        self.attributes.lock().unwrap().max_size = size;
+    }
+
+    fn set_cursor_visible(&self, visible: bool) {
+        self.attributes.lock().unwrap().cursor_visible = visible;
+    }
+
+    // there's no platform to refuse a grab.
+    fn set_cursor_grab(&self, grab: CursorGrab) -> CursorGrab {
+        self.attributes.lock().unwrap().cursor_grab = grab;
+        grab
+    }
+
+    fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.attributes.lock().unwrap().cursor_icon = icon;
+    }
+
+    fn frame_sink(&self) -> Option<&dyn FrameSink> {
+        Some(self)
+    }
+}
+
+impl FrameSink for HeadlessWindow {
+    fn present(&self, frame: PresentedFrame) {
+        *self.latest_frame.lock().unwrap() = Some(frame);
+    }
+}
+
+/// [`MainThreadSpawner`] for headless surfaces, that runs closures inline.
+#[derive(Clone, Debug)]
+pub struct HeadlessSpawner {
+    state: Arc<Mutex<HeadlessState>>,
+    // notified whenever a surface is created, see `wait_for_surface`.
+    surface_created: Arc<Condvar>,
+}
+
+#[derive(Debug)]
+struct HeadlessState {
+    // weak, so that dropped surfaces are freed. Pruned as they're found dead.
+    surfaces: Vec<WeakSurface>,
+    frame_interval: Duration,
+    now: Duration,
+    next_frame: Duration,
+}
+
+impl HeadlessState {
+    fn live_surfaces(&mut self) -> Vec<Surface> {
+        let mut live = Vec::with_capacity(self.surfaces.len());
+        self.surfaces.retain(|surface| match surface.upgrade() {
+            Some(surface) => {
+                live.push(surface);
+                true
+            }
+            None => false,
+        });
+        live
+    }
+
+    fn animation_frame(&mut self, timestamp: Duration) {
+        for surface in self.live_surfaces() {
+            let window = surface.headless().unwrap();
+            if window.take_resized() {
+                surface.canvas_resize(ResizeEvent {
+                    width: surface.width(),
+                    height: surface.height(),
+                });
+            }
+            surface.animation_frame_at(timestamp);
+        }
+    }
+}
+
+impl HeadlessSpawner {
+    pub fn new() -> Self {
+        Self::with_frame_interval(DEFAULT_FRAME_INTERVAL)
+    }
+
+    /// Create a spawner whose virtual clock sends an animation frame every `frame_interval`.
+    pub fn with_frame_interval(frame_interval: Duration) -> Self {
+        assert!(!frame_interval.is_zero(), "frame interval must not be zero");
+        Self {
+            state: Arc::new(Mutex::new(HeadlessState {
+                surfaces: Vec::new(),
+                frame_interval,
+                now: Duration::ZERO,
+                next_frame: Duration::ZERO,
+            })),
+            surface_created: Arc::new(Condvar::new()),
+        }
+    }
+
+    /// All surfaces created through this spawner that weren't dropped yet, oldest first.
+    pub fn surfaces(&self) -> Vec<Surface> {
+        self.state.lock().unwrap().live_surfaces()
+    }
+
+    /// Surface number `index` of [`HeadlessSpawner::surfaces`], waiting up to `timeout` for the guest to create it.
+    pub fn wait_for_surface(&self, index: usize, timeout: Duration) -> Option<Surface> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(surface) = state.live_surfaces().into_iter().nth(index) {
+                return Some(surface);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            state = self
+                .surface_created
+                .wait_timeout(state, remaining)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Send an animation frame to every surface, after any pending resize events.
+    ///
+    /// Frames are timestamped with the virtual clock.
+    pub fn animation_frame(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = state.now;
+        state.animation_frame(now);
+    }
+
+    /// Time elapsed on the virtual clock.
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Advance the virtual clock, sending an animation frame for every frame interval that passes.
+    ///
+    /// Frames the guest hasn't received yet are merged, so advancing by several intervals at once doesn't queue up several frames.
+    pub fn advance(&self, by: Duration) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.now += by;
+        while state.next_frame <= state.now {
+            let timestamp = state.next_frame;
+            state.next_frame += state.frame_interval;
+            state.animation_frame(timestamp);
+        }
+    }
+}
+
+impl Default for HeadlessSpawner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MainThreadSpawner for HeadlessSpawner {
+    async fn spawn<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        f()
+    }
+
+    fn create_surface(&self, desc: SurfaceDesc) -> Pin<Box<dyn Future<Output = Surface> + Send>> {
+        let window = HeadlessWindow::from_desc(&desc);
+        let surface = Surface::new(Box::new(window));
+        let mut state = self.state.lock().unwrap();
+        // prunes dropped surfaces, so creating and dropping many doesn't grow the list.
+        state.live_surfaces();
+        state.surfaces.push(surface.downgrade());
+        self.surface_created.notify_all();
+        Box::pin(async move { surface })
+    }
+}
+
+/// A description that leaves everything to the host, for tests to start from.
+#[cfg(test)]
+pub(crate) fn test_desc() -> SurfaceDesc {
+    SurfaceDesc {
+        height: None,
+        width: None,
+        title: None,
+        resizable: None,
+        decorations: None,
+        fullscreen: None,
+        visible: None,
+    }
+}
+
+/// A surface created on `spawner` from [`test_desc`].
+#[cfg(test)]
+pub(crate) fn test_surface(spawner: &HeadlessSpawner) -> Surface {
+    futures::executor::block_on(spawner.create_surface(test_desc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::{
+        wasi_gfx::surface::surface::{CreateDesc, HostSurface},
+        SurfaceCtx,
+    };
+
+    fn desc() -> CreateDesc {
+        CreateDesc {
+            height: None,
+            width: None,
+        }
+    }
+
+    #[test]
+    fn dropped_surfaces_are_freed() {
+        let spawner = HeadlessSpawner::new();
+        let mut table = wasmtime_wasi::ResourceTable::new();
+        let mut ctx = SurfaceCtx {
+            table: &mut table,
+            main_thread_spawner: &spawner,
+        };
+
+        let mut dropped = Vec::new();
+        for _ in 0..1000 {
+            let surface = HostSurface::new(&mut ctx, desc()).unwrap();
+            dropped.push(ctx.table.get(&surface).unwrap().downgrade());
+            HostSurface::drop(&mut ctx, surface).unwrap();
+        }
+        let kept = HostSurface::new(&mut ctx, desc()).unwrap();
+        spawner.advance(Duration::from_secs(1));
+
+        assert!(dropped.iter().all(|surface| surface.upgrade().is_none()));
+        assert_eq!(spawner.surfaces().len(), 1);
+        assert_eq!(spawner.state.lock().unwrap().surfaces.len(), 1);
+        assert!(ctx.table.get(&kept).is_ok());
+    }
+
+    #[test]
+    fn surface_ext_sets_attributes() {
+        use crate::surface::wasi_gfx::surface_ext::surface_ext::Host;
+        use wasmtime::component::Resource;
+
+        let spawner = HeadlessSpawner::new();
+        let mut table = wasmtime_wasi::ResourceTable::new();
+        let mut ctx = SurfaceCtx {
+            table: &mut table,
+            main_thread_spawner: &spawner,
+        };
+
+        let surface = Host::create_surface(
+            &mut ctx,
+            SurfaceDesc {
+                title: Some("created".to_string()),
+                ..test_desc()
+            },
+        )
+        .unwrap();
+        let surfaces = spawner.surfaces();
+        let window = surfaces[0].headless().unwrap();
+        assert_eq!(window.attributes().title, "created");
+        assert!(!Host::fullscreen(&mut ctx, Resource::new_borrow(surface.rep())).unwrap());
+
+        Host::set_title(
+            &mut ctx,
+            Resource::new_borrow(surface.rep()),
+            "set".to_string(),
+        )
+        .unwrap();
+        Host::set_fullscreen(&mut ctx, Resource::new_borrow(surface.rep()), true).unwrap();
+        let grab = Host::set_cursor_grab(
+            &mut ctx,
+            Resource::new_borrow(surface.rep()),
+            CursorGrab::Locked,
+        )
+        .unwrap();
+
+        let attributes = window.attributes();
+        assert_eq!(attributes.title, "set");
+        assert!(attributes.fullscreen);
+        assert_eq!(attributes.cursor_grab, CursorGrab::Locked);
+        assert_eq!(grab, CursorGrab::Locked);
+        assert!(Host::fullscreen(&mut ctx, Resource::new_borrow(surface.rep())).unwrap());
+    }
+
+    #[test]
+    fn wait_for_surface() {
+        let spawner = HeadlessSpawner::new();
+        assert!(spawner
+            .wait_for_surface(0, Duration::from_millis(10))
+            .is_none());
+
+        let created = std::thread::spawn({
+            let spawner = spawner.clone();
+            move || {
+                futures::executor::block_on(spawner.create_surface(SurfaceDesc {
+                    height: Some(100),
+                    width: Some(200),
+                    ..test_desc()
+                }))
+            }
+        });
+        let surface = spawner
+            .wait_for_surface(0, Duration::from_secs(10))
+            .unwrap();
+        let _created = created.join().unwrap();
+        assert_eq!((surface.width(), surface.height()), (200, 100));
+    }
+
+    // `SurfaceFrameBufferCtx` borrows a wgpu instance, so this needs `surface-webgpu` too.
+    #[cfg(all(feature = "surface-frame-buffer", feature = "surface-webgpu"))]
+    #[test]
+    fn frame_buffer_presents_into_memory() {
+        use crate::surface_frame_buffer::{
+            wasi_gfx::surface::surface_frame_buffer::HostContext, SurfaceFrameBufferCtx,
+        };
+        use wasi_webgpu_wasmtime::reexports::{wgpu_core, wgpu_types};
+        use wasmtime::component::Resource;
+
+        let spawner = HeadlessSpawner::new();
+        let instance = Arc::new(wgpu_core::global::Global::new(
+            "headless-test",
+            wgpu_types::InstanceDescriptor::new_without_display_handle(),
+            None,
+        ));
+        let mut table = wasmtime_wasi::ResourceTable::new();
+        let surface = HostSurface::new(
+            &mut SurfaceCtx {
+                table: &mut table,
+                main_thread_spawner: &spawner,
+            },
+            CreateDesc {
+                width: Some(2),
+                height: Some(1),
+            },
+        )
+        .unwrap();
+        let window = table.get(&surface).unwrap().headless().unwrap();
+        assert!(window.latest_frame().is_none());
+
+        let mut ctx = SurfaceFrameBufferCtx {
+            table: &mut table,
+            instance: &instance,
+            main_thread_spawner: &spawner,
+        };
+        let context = HostContext::new(&mut ctx, Resource::new_borrow(surface.rep())).unwrap();
+        let buffer =
+            HostContext::get_current_buffer(&mut ctx, Resource::new_borrow(context.rep())).unwrap();
+        let pixels: [u32; 2] = [0x00ff0000, 0x000000ff];
+        ctx.table
+            .get_mut(&buffer)
+            .unwrap()
+            .buffer
+            .set_buffer(bytemuck::cast_slice(&pixels))
+            .unwrap();
+        HostContext::present(&mut ctx, Resource::new_borrow(context.rep())).unwrap();
+
+        let window = table.get(&surface).unwrap().headless().unwrap();
+        let Some(HeadlessFrame::FrameBuffer {
+            width,
+            height,
+            pixels: presented,
+        }) = window.latest_frame()
+        else {
+            panic!("expected a frame buffer frame");
+        };
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(presented, pixels);
+    }
+}
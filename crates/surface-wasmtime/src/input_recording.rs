@@ -0,0 +1,213 @@
+//! Recording input delivered to surfaces, and replaying it, so that a bug seen by a tester can be reproduced.
+//!
+//! Recordings are JSON lines, one [`RecordedEvent`] per line.
+
+use std::{
+    fs::File,
+    io::{self, LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input_script::{InputEvent, InputScript, InputScriptError},
+    surface::Surface,
+};
+
+/// An input event, as delivered to a surface.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Index of the surface, in order of creation.
+    pub surface: usize,
+    /// Number of animation frames sent to the surface before this event, see [`Surface::frame_index`].
+    pub frame: u64,
+    /// Time since the recording started.
+    pub time: Duration,
+    #[serde(flatten)]
+    pub event: InputEvent,
+}
+
+/// Writes every recorded event to a file as it happens.
+///
+/// Lines are flushed right away, so that nothing is lost if the process exits without unwinding.
+pub struct InputRecorder {
+    writer: Mutex<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl InputRecorder {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(LineWriter::new(File::create(path)?)))
+    }
+
+    /// Record an event delivered to `surface`, which is surface number `index`.
+    pub fn record(&self, index: usize, surface: &Surface, event: InputEvent) {
+        let event = RecordedEvent {
+            surface: index,
+            frame: surface.frame_index(),
+            time: self.start.elapsed(),
+            event,
+        };
+        let mut writer = self.writer.lock().unwrap();
+        let res = serde_json::to_writer(&mut *writer, &event)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(e) = res {
+            log::warn!("failed to record input event: {e}");
+        }
+    }
+}
+
+impl std::fmt::Debug for InputRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InputRecorder")
+            .field("writer", &"<Boxed writer>")
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+/// A recording written by an [`InputRecorder`].
+#[derive(Clone, Debug, Default)]
+pub struct InputRecording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl InputRecording {
+    pub fn from_json_lines(json_lines: &str) -> Result<Self, InputScriptError> {
+        let events = json_lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(InputScriptError::Json)?;
+        Ok(Self { events })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputScriptError> {
+        let json_lines = std::fs::read_to_string(path).map_err(InputScriptError::Io)?;
+        Self::from_json_lines(&json_lines)
+    }
+
+    /// The events delivered to `surface`, as a script that sends each event on the frame it was recorded on.
+    ///
+    /// Timestamps are dropped, frames are what keeps the replay deterministic.
+    pub fn script(&self, surface: usize) -> InputScript {
+        let mut events = Vec::new();
+        let mut frame = 0;
+        for recorded in self.events.iter().filter(|e| e.surface == surface) {
+            while frame < recorded.frame {
+                events.push(InputEvent::Frame);
+                frame += 1;
+            }
+            events.push(recorded.event.clone());
+        }
+        // so that the guest gets a chance to handle the last events.
+        events.push(InputEvent::Frame);
+        InputScript { events }
+    }
+
+    /// Feed the events recorded for surface number `surface_index` into `surface`. See [`InputScript::play`].
+    ///
+    /// The surface shouldn't be getting frames from anywhere else, e.g. use a headless surface, or replay won't be exact.
+    pub fn replay(
+        &self,
+        surface: &Surface,
+        surface_index: usize,
+        frame_timeout: Duration,
+    ) -> Result<(), InputScriptError> {
+        self.script(surface_index).play(surface, frame_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{test_surface, HeadlessSpawner},
+        input_script::TouchPoint,
+    };
+    use std::sync::Arc;
+
+    // lets the test read back what the recorder wrote.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let spawner = HeadlessSpawner::new();
+        let surface = test_surface(&spawner);
+        let buffer = SharedBuffer::default();
+        let recorder = InputRecorder::new(buffer.clone());
+
+        let touch = TouchPoint {
+            id: 0,
+            x: 1.0,
+            y: 2.0,
+        };
+        recorder.record(0, &surface, InputEvent::TouchStart(touch));
+        surface.animation_frame();
+        surface.animation_frame();
+        recorder.record(0, &surface, InputEvent::TouchEnd(touch));
+        recorder.record(1, &surface, InputEvent::CloseRequested);
+
+        let json_lines = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(json_lines.lines().count(), 3);
+        let recording = InputRecording::from_json_lines(&json_lines).unwrap();
+        assert_eq!(
+            recording
+                .events
+                .iter()
+                .map(|e| (e.surface, e.frame))
+                .collect::<Vec<_>>(),
+            [(0, 0), (0, 2), (1, 2)]
+        );
+
+        // each event is sent on the frame it was recorded on.
+        assert!(matches!(
+            &recording.script(0).events[..],
+            [
+                InputEvent::TouchStart(TouchPoint { id: 0, .. }),
+                InputEvent::Frame,
+                InputEvent::Frame,
+                InputEvent::TouchEnd(TouchPoint { id: 0, .. }),
+                InputEvent::Frame,
+            ]
+        ));
+        assert!(matches!(
+            &recording.script(1).events[..],
+            [
+                InputEvent::Frame,
+                InputEvent::Frame,
+                InputEvent::CloseRequested,
+                InputEvent::Frame,
+            ]
+        ));
+
+        let replayed = test_surface(&spawner);
+        recording
+            .replay(&replayed, 0, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(replayed.frame_index(), 3);
+    }
+}
@@ -0,0 +1,299 @@
+//! Scripted input, for clicking through guests in tests without a human.
+//!
+//! An [`InputScript`] is a timeline of input events, separated by animation frames.
+//! Events between two frames are sent together, and each frame waits for the guest to receive it before moving on,
+//! so a guest that handles input once per frame sees the same events on the same frame every run.
+
+use std::{fmt::Display, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::surface::{
+    pointer_button_mask, touch_pointer_id, FocusEvent, KeyEvent, PinchEvent, PointerButton,
+    PointerEvent, PointerType, ResizeEvent, RotateEvent, Surface, VisibilityEvent, WheelEvent,
+};
+
+/// A timeline of input events to play against a [`Surface`].
+///
+/// In JSON, with pointer events shortened to the fields that matter here:
+/// ```json
+/// { "events": [
+///     { "type": "pointer-down", "x": 10.0, "y": 20.0, "button": "Primary", "buttons": 1, ... },
+///     { "type": "frame" },
+///     { "type": "pointer-up", "x": 10.0, "y": 20.0, "button": "Primary", "buttons": 0, ... },
+///     { "type": "frame" },
+///     { "type": "touch-start", "id": 0, "x": 10.0, "y": 20.0 },
+///     { "type": "touch-start", "id": 1, "x": 50.0, "y": 20.0 },
+///     { "type": "frame" },
+///     { "type": "touch-end", "id": 0, "x": 10.0, "y": 20.0 },
+///     { "type": "touch-cancel", "id": 1, "x": 50.0, "y": 20.0 },
+///     { "type": "frame" }
+/// ] }
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputScript {
+    pub events: Vec<InputEvent>,
+}
+
+/// A single step of an [`InputScript`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum InputEvent {
+    PointerDown(PointerEvent),
+    PointerUp(PointerEvent),
+    PointerMove(PointerEvent),
+    PointerCancel(PointerEvent),
+    PointerEnter(PointerEvent),
+    PointerLeave(PointerEvent),
+    /// Shorthands for pointer events of touches.
+    TouchStart(TouchPoint),
+    TouchMove(TouchPoint),
+    TouchEnd(TouchPoint),
+    TouchCancel(TouchPoint),
+    KeyDown(KeyEvent),
+    KeyUp(KeyEvent),
+    Wheel(WheelEvent),
+    Pinch(PinchEvent),
+    Rotate(RotateEvent),
+    Focus(FocusEvent),
+    /// Only tells the guest, frames keep coming when scripted.
+    VisibilityChange(VisibilityEvent),
+    CloseRequested,
+    /// Resizes headless windows, other windows only get the resize event.
+    Resize(ResizeEvent),
+    /// Send an animation frame, and wait for the guest to receive it.
+    Frame,
+}
+
+/// A finger on a touch screen, for [`InputEvent::TouchStart`] and friends.
+///
+/// Sent as a pointer event with pointer type `touch`, the way the winit backend sends touches.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TouchPoint {
+    /// Tells fingers apart, must stay the same from start to end.
+    pub id: u64,
+    pub x: f64,
+    pub y: f64,
+}
+
+impl TouchPoint {
+    fn pointer_event(&self, button: Option<PointerButton>, contact: bool) -> PointerEvent {
+        PointerEvent {
+            x: self.x,
+            y: self.y,
+            pointer_id: touch_pointer_id(self.id),
+            pointer_type: PointerType::Touch,
+            button,
+            buttons: if contact {
+                pointer_button_mask(PointerButton::Primary)
+            } else {
+                0
+            },
+            pressure: if contact { 0.5 } else { 0.0 },
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            alt_key: false,
+            ctrl_key: false,
+            meta_key: false,
+            shift_key: false,
+        }
+    }
+}
+
+impl InputScript {
+    pub fn from_json(json: &str) -> Result<Self, InputScriptError> {
+        serde_json::from_str(json).map_err(InputScriptError::Json)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, InputScriptError> {
+        ron::from_str(ron).map_err(InputScriptError::Ron)
+    }
+
+    /// Play the script against `surface`, blocking until the guest received every frame.
+    ///
+    /// Must not be called from the thread running the guest, since the guest has to keep running to receive frames.
+    /// Fails if the guest doesn't receive a frame within `frame_timeout`.
+    pub fn play(&self, surface: &Surface, frame_timeout: Duration) -> Result<(), InputScriptError> {
+        let mut frame = 0;
+        for event in &self.events {
+            match event {
+                InputEvent::PointerDown(event) => surface.pointer_down(*event),
+                InputEvent::PointerUp(event) => surface.pointer_up(*event),
+                InputEvent::PointerMove(event) => surface.pointer_move(*event),
+                InputEvent::PointerCancel(event) => surface.pointer_cancel(*event),
+                InputEvent::PointerEnter(event) => surface.pointer_enter(*event),
+                InputEvent::PointerLeave(event) => surface.pointer_leave(*event),
+                InputEvent::TouchStart(touch) => {
+                    surface.pointer_down(touch.pointer_event(Some(PointerButton::Primary), true))
+                }
+                InputEvent::TouchMove(touch) => {
+                    surface.pointer_move(touch.pointer_event(None, true))
+                }
+                InputEvent::TouchEnd(touch) => {
+                    surface.pointer_up(touch.pointer_event(Some(PointerButton::Primary), false))
+                }
+                InputEvent::TouchCancel(touch) => {
+                    surface.pointer_cancel(touch.pointer_event(None, false))
+                }
+                InputEvent::KeyDown(event) => surface.key_down(event.clone()),
+                InputEvent::KeyUp(event) => surface.key_up(event.clone()),
+                InputEvent::Wheel(event) => surface.wheel(*event),
+                InputEvent::Pinch(event) => surface.pinch(*event),
+                InputEvent::Rotate(event) => surface.rotate(*event),
+                InputEvent::Focus(event) => surface.focus(*event),
+                InputEvent::VisibilityChange(event) => surface.visibility_change(*event),
+                InputEvent::CloseRequested => {
+                    surface.close_requested();
+                }
+                InputEvent::Resize(event) => {
+                    if let Some(window) = surface.headless() {
+                        window.set_size(event.width, event.height);
+                        // sending the event here, so the spawner shouldn't send it again.
+                        window.take_resized();
+                    }
+                    surface.canvas_resize(*event);
+                }
+                InputEvent::Frame => {
+                    surface.animation_frame();
+                    if !surface.wait_frame_received(frame_timeout) {
+                        return Err(InputScriptError::FrameTimeout { frame });
+                    }
+                    frame += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum InputScriptError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Ron(ron::error::SpannedError),
+    /// The guest didn't receive frame number `frame` (counting from zero) in time.
+    FrameTimeout {
+        frame: usize,
+    },
+}
+
+impl Display for InputScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputScriptError::Io(e) => write!(f, "failed to read input: {e}"),
+            InputScriptError::Json(e) => write!(f, "invalid JSON input script: {e}"),
+            InputScriptError::Ron(e) => write!(f, "invalid RON input script: {e}"),
+            InputScriptError::FrameTimeout { frame } => {
+                write!(f, "guest didn't receive frame {frame} in time")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InputScriptError::Io(e) => Some(e),
+            InputScriptError::Json(e) => Some(e),
+            InputScriptError::Ron(e) => Some(e),
+            InputScriptError::FrameTimeout { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::Key;
+
+    #[test]
+    fn parse_json() {
+        let script = InputScript::from_json(
+            r#"{ "events": [
+                { "type": "key-down", "key": "KeyA", "text": "a", "alt_key": false, "ctrl_key": false, "meta_key": false, "shift_key": false },
+                { "type": "frame" },
+                { "type": "touch-start", "id": 0, "x": 10.0, "y": 20.0 },
+                { "type": "resize", "width": 640, "height": 480 },
+                { "type": "close-requested" }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &script.events[..],
+            [
+                InputEvent::KeyDown(KeyEvent {
+                    key: Some(Key::KeyA),
+                    ..
+                }),
+                InputEvent::Frame,
+                InputEvent::TouchStart(TouchPoint { id: 0, .. }),
+                InputEvent::Resize(ResizeEvent {
+                    width: 640,
+                    height: 480
+                }),
+                InputEvent::CloseRequested,
+            ]
+        ));
+
+        assert!(matches!(
+            InputScript::from_json(r#"{ "events": [{ "type": "teleport" }] }"#),
+            Err(InputScriptError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn parse_ron() {
+        let script = InputScript::from_ron(
+            r#"(events: [
+                {"type": "touch-move", "id": 1, "x": 1.5, "y": 2.5},
+                {"type": "frame"},
+            ])"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &script.events[..],
+            [
+                InputEvent::TouchMove(TouchPoint {
+                    id: 1,
+                    x: 1.5,
+                    y: 2.5
+                }),
+                InputEvent::Frame,
+            ]
+        ));
+
+        assert!(matches!(
+            InputScript::from_ron("(events: [Frame"),
+            Err(InputScriptError::Ron(_))
+        ));
+    }
+
+    // scripts written by `InputRecording::script` and friends read back the same.
+    #[test]
+    fn round_trip() {
+        let script = InputScript {
+            events: vec![
+                InputEvent::TouchEnd(TouchPoint {
+                    id: 3,
+                    x: 4.0,
+                    y: 5.0,
+                }),
+                InputEvent::Frame,
+            ],
+        };
+        let json = serde_json::to_string(&script).unwrap();
+        let ron = ron::to_string(&script).unwrap();
+        for parsed in [
+            InputScript::from_json(&json).unwrap(),
+            InputScript::from_ron(&ron).unwrap(),
+        ] {
+            assert!(matches!(
+                &parsed.events[..],
+                [
+                    InputEvent::TouchEnd(TouchPoint { id: 3, .. }),
+                    InputEvent::Frame
+                ]
+            ));
+        }
+    }
+}
@@ -1,9 +1,23 @@
 mod surface;
 pub use surface::{
-    add_to_linker as add_surface_to_linker, GfxWindow, Key, KeyEvent, MainThreadSpawner,
-    PointerEvent, ResizeEvent, Surface, SurfaceCtx, SurfaceCtxView, SurfaceDesc,
+    add_to_linker as add_surface_to_linker, CloseRequestedEvent, CursorGrab, CursorIcon,
+    FocusEvent, FrameSink, GesturePhase, GfxWindow, Key, KeyEvent, MainThreadSpawner, PinchEvent,
+    PointerButton, PointerEvent, PointerType, PresentedFrame, ResizeEvent, RotateEvent, Surface,
+    SurfaceCtx, SurfaceCtxView, SurfaceDesc, VisibilityEvent, WeakSurface, WheelDeltaMode,
+    WheelEvent, WindowSize, CLOSE_REQUEST_TIMEOUT,
 };
 
+pub mod headless;
+
+#[cfg(feature = "input-script")]
+mod input_script;
+#[cfg(feature = "input-script")]
+pub use input_script::{InputEvent, InputScript, InputScriptError, TouchPoint};
+#[cfg(feature = "input-script")]
+mod input_recording;
+#[cfg(feature = "input-script")]
+pub use input_recording::{InputRecorder, InputRecording, RecordedEvent};
+
 #[cfg(feature = "winit")]
 pub mod winit;
 
@@ -11,7 +25,8 @@ pub mod winit;
 mod surface_webgpu;
 #[cfg(feature = "surface-webgpu")]
 pub use surface_webgpu::{
-    add_to_linker as add_surface_webgpu_to_linker, SurfaceWebgpuCtx, SurfaceWebgpuCtxView,
+    add_to_linker as add_surface_webgpu_to_linker, Context as SurfaceWebgpuContext, PresentPolicy,
+    SurfaceWebgpuCtx, SurfaceWebgpuCtxView,
 };
 
 #[cfg(feature = "surface-frame-buffer")]
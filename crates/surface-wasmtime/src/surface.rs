@@ -1,37 +1,117 @@
+use crate::headless::HeadlessWindow;
+use futures::{Stream, StreamExt};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
-use shared::channel_to_stream;
-use std::{fmt::Debug, future::Future, marker::PhantomData, pin::Pin, sync::Arc};
+use shared::{channel_to_stream, StreamPipe};
+use std::{
+    any::Any,
+    fmt::Debug,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, Weak},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 use wasi_gfx::surface::surface;
-pub use wasi_gfx::surface::surface::{
-    FrameEvent, Key, KeyEvent, PointerEvent, {CreateDesc as SurfaceDesc, ResizeEvent},
+pub use wasi_gfx::surface::surface::{Key, KeyEvent, ResizeEvent};
+use wasi_gfx::surface_ext::surface_ext;
+pub use wasi_gfx::surface_ext::surface_ext::{
+    CloseRequestedEvent, CreateDesc as SurfaceDesc, CursorGrab, CursorIcon, FocusEvent, FrameEvent,
+    GesturePhase, PinchEvent, PointerButton, PointerEvent, PointerType, RotateEvent,
+    VisibilityEvent, WheelDeltaMode, WheelEvent, WindowSize,
 };
 use wasmtime::component::{Access, HasData, Resource, StreamReader};
 
-wasmtime::component::bindgen!({
-    world: "wasi-gfx:surface/imports",
-    require_store_data_send: true,
-    imports: {
-        "wasi-gfx:surface/surface.[method]surface.on-pointer-down": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-pointer-move": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-key-up": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-pointer-up": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-key-down": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-resize": store | trappable,
-        "wasi-gfx:surface/surface.[method]surface.on-frame": store | trappable,
-        default: trappable,
-    },
-    with: {
-        "wasi-gfx:surface/surface.surface": Surface,
-    },
-});
+// Input scripts are read from and written to files, so the types they use derive serde when they're enabled.
+macro_rules! bindgen_surface {
+    ($($derives:tt)*) => {
+        wasmtime::component::bindgen!({
+            world: "wasi-gfx:surface-ext/imports",
+            require_store_data_send: true,
+            additional_derives: [$($derives)*],
+            imports: {
+                "wasi-gfx:surface/surface.[method]surface.on-pointer-down": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-pointer-move": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-key-up": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-pointer-up": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-key-down": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-resize": store | trappable,
+                "wasi-gfx:surface/surface.[method]surface.on-frame": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-frame": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-up": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-down": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-move": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-cancel": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-wheel": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pinch": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-rotate": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-enter": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-pointer-leave": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-focus": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-visibility-change": store | trappable,
+                "wasi-gfx:surface-ext/surface-ext.on-close-requested": store | trappable,
+                default: trappable,
+            },
+            with: {
+                "wasi-gfx:surface/surface.surface": Surface,
+            },
+        });
+    };
+}
+#[cfg(feature = "input-script")]
+bindgen_surface!(serde::Serialize, serde::Deserialize);
+#[cfg(not(feature = "input-script"))]
+bindgen_surface!();
 
 // types
 
 /// Any type implementing GfxWindow can be used to back a wasi-gfx:surface
-pub trait GfxWindow: HasDisplayHandle + HasWindowHandle {
+pub trait GfxWindow: HasDisplayHandle + HasWindowHandle + Any {
     fn height(&self) -> u32;
     fn width(&self) -> u32;
     fn request_set_size(&self, width: Option<u32>, height: Option<u32>);
+    // Window management. Defaults do nothing, for windows that have no such thing.
+    fn set_title(&self, _title: &str) {}
+    fn fullscreen(&self) -> bool {
+        false
+    }
+    fn set_fullscreen(&self, _fullscreen: bool) {}
+    fn set_visible(&self, _visible: bool) {}
+    fn set_resizable(&self, _resizable: bool) {}
+    fn set_decorations(&self, _decorations: bool) {}
+    fn set_min_size(&self, _size: Option<WindowSize>) {}
+    fn set_max_size(&self, _size: Option<WindowSize>) {}
+    fn set_cursor_visible(&self, _visible: bool) {}
+    /// Returns the grab that was applied.
+    fn set_cursor_grab(&self, _grab: CursorGrab) -> CursorGrab {
+        CursorGrab::None
+    }
+    fn set_cursor_icon(&self, _icon: CursorIcon) {}
+    /// Windows without a display return where contexts present to instead, see [`FrameSink`].
+    fn frame_sink(&self) -> Option<&dyn FrameSink> {
+        None
+    }
+    /// Called right before a context presents to the window, so the windowing system can throttle frames to the display.
+    fn pre_present_notify(&self) {}
+}
+
+/// Receives the frames presented to a window that has no display, e.g. [`HeadlessWindow`].
+pub trait FrameSink {
+    fn present(&self, frame: PresentedFrame);
+}
+
+/// A frame presented to a [`FrameSink`].
+#[derive(Clone, Debug)]
+pub enum PresentedFrame {
+    /// Presented by a `surface-frame-buffer` context. Each pixel is a `0RGB` `u32`, same as softbuffer.
+    FrameBuffer {
+        width: u32,
+        height: u32,
+        pixels: Vec<u32>,
+    },
+    /// Presented by a `surface-webgpu` context. The frame is still on the GPU, and only read back when needed.
+    #[cfg(feature = "surface-webgpu")]
+    Webgpu(wasi_webgpu_wasmtime::PresentedCanvasFrame),
 }
 
 #[derive(Clone, Debug)]
@@ -45,9 +125,13 @@ impl Surface {
         let (mut pointer_down_sender, pointer_down_receiver) = async_broadcast::broadcast(5);
         let pointer_down_receiver = pointer_down_receiver.deactivate();
         pointer_down_sender.set_overflow(true);
-        let (mut pointer_move_sender, pointer_move_receiver) = async_broadcast::broadcast(1);
+        // room for a move from every finger.
+        let (mut pointer_move_sender, pointer_move_receiver) = async_broadcast::broadcast(10);
         let pointer_move_receiver = pointer_move_receiver.deactivate();
         pointer_move_sender.set_overflow(true);
+        let (mut pointer_cancel_sender, pointer_cancel_receiver) = async_broadcast::broadcast(5);
+        let pointer_cancel_receiver = pointer_cancel_receiver.deactivate();
+        pointer_cancel_sender.set_overflow(true);
         let (mut key_up_sender, key_up_receiver) = async_broadcast::broadcast(5);
         let key_up_receiver = key_up_receiver.deactivate();
         key_up_sender.set_overflow(true);
@@ -60,6 +144,30 @@ impl Surface {
         let (mut frame_sender, frame_receiver) = async_broadcast::broadcast(1);
         let frame_receiver = frame_receiver.deactivate();
         frame_sender.set_overflow(true);
+        let (mut wheel_sender, wheel_receiver) = async_broadcast::broadcast(5);
+        let wheel_receiver = wheel_receiver.deactivate();
+        wheel_sender.set_overflow(true);
+        let (mut pinch_sender, pinch_receiver) = async_broadcast::broadcast(5);
+        let pinch_receiver = pinch_receiver.deactivate();
+        pinch_sender.set_overflow(true);
+        let (mut rotate_sender, rotate_receiver) = async_broadcast::broadcast(5);
+        let rotate_receiver = rotate_receiver.deactivate();
+        rotate_sender.set_overflow(true);
+        let (mut pointer_enter_sender, pointer_enter_receiver) = async_broadcast::broadcast(5);
+        let pointer_enter_receiver = pointer_enter_receiver.deactivate();
+        pointer_enter_sender.set_overflow(true);
+        let (mut pointer_leave_sender, pointer_leave_receiver) = async_broadcast::broadcast(5);
+        let pointer_leave_receiver = pointer_leave_receiver.deactivate();
+        pointer_leave_sender.set_overflow(true);
+        let (mut focus_sender, focus_receiver) = async_broadcast::broadcast(5);
+        let focus_receiver = focus_receiver.deactivate();
+        focus_sender.set_overflow(true);
+        let (mut visibility_sender, visibility_receiver) = async_broadcast::broadcast(5);
+        let visibility_receiver = visibility_receiver.deactivate();
+        visibility_sender.set_overflow(true);
+        let (mut close_requested_sender, close_requested_receiver) = async_broadcast::broadcast(5);
+        let close_requested_receiver = close_requested_receiver.deactivate();
+        close_requested_sender.set_overflow(true);
         Surface(Arc::new(SurfaceInner {
             window,
             pointer_up_sender,
@@ -68,6 +176,9 @@ impl Surface {
             _pointer_down_receiver: pointer_down_receiver,
             pointer_move_sender,
             _pointer_move_receiver: pointer_move_receiver,
+            pending_pointer_move: Arc::new(Mutex::new(Vec::new())),
+            pointer_cancel_sender,
+            _pointer_cancel_receiver: pointer_cancel_receiver,
             key_up_sender,
             _key_up_receiver: key_up_receiver,
             key_down_sender,
@@ -76,6 +187,32 @@ impl Surface {
             _resize_receiver: resize_receiver,
             frame_sender,
             _frame_receiver: frame_receiver,
+            wheel_sender,
+            _wheel_receiver: wheel_receiver,
+            pending_wheel: Arc::new(Mutex::new(Vec::new())),
+            pinch_sender,
+            _pinch_receiver: pinch_receiver,
+            pending_pinch: Arc::new(Mutex::new(Vec::new())),
+            rotate_sender,
+            _rotate_receiver: rotate_receiver,
+            pending_rotate: Arc::new(Mutex::new(Vec::new())),
+            pointer_enter_sender,
+            _pointer_enter_receiver: pointer_enter_receiver,
+            pointer_leave_sender,
+            _pointer_leave_receiver: pointer_leave_receiver,
+            focus_sender,
+            _focus_receiver: focus_receiver,
+            visibility_sender,
+            _visibility_receiver: visibility_receiver,
+            close_requested_sender,
+            _close_requested_receiver: close_requested_receiver,
+            close_requests: Arc::new(Mutex::new(CloseRequests::default())),
+            created: Instant::now(),
+            frame_clock: Mutex::new(FrameClock {
+                index: 0,
+                last: None,
+            }),
+            frame_received: Arc::new(FrameReceived::default()),
         }))
     }
 
@@ -91,19 +228,103 @@ impl Surface {
         self.0.window.request_set_size(width, height);
     }
 
+    pub fn set_title(&self, title: &str) {
+        self.0.window.set_title(title);
+    }
+
+    pub fn fullscreen(&self) -> bool {
+        self.0.window.fullscreen()
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.0.window.set_fullscreen(fullscreen);
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.0.window.set_visible(visible);
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        self.0.window.set_resizable(resizable);
+    }
+
+    pub fn set_decorations(&self, decorations: bool) {
+        self.0.window.set_decorations(decorations);
+    }
+
+    pub fn set_min_size(&self, size: Option<WindowSize>) {
+        self.0.window.set_min_size(size);
+    }
+
+    pub fn set_max_size(&self, size: Option<WindowSize>) {
+        self.0.window.set_max_size(size);
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.0.window.set_cursor_visible(visible);
+    }
+
+    pub fn set_cursor_grab(&self, grab: CursorGrab) -> CursorGrab {
+        self.0.window.set_cursor_grab(grab)
+    }
+
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.0.window.set_cursor_icon(icon);
+    }
+
+    /// The headless window backing this surface, if it's not backed by a real window.
+    pub fn headless(&self) -> Option<&HeadlessWindow> {
+        let window: &dyn Any = &*self.0.window;
+        window.downcast_ref()
+    }
+
+    /// Where contexts on this surface present to, if it has no display to present to.
+    pub fn frame_sink(&self) -> Option<&dyn FrameSink> {
+        self.0.window.frame_sink()
+    }
+
     /// clone the Arc reference. i.e shallow clone
     pub fn arc_clone(&self) -> Self {
         Surface(Arc::clone(&self.0))
     }
 
+    /// A reference that doesn't keep the surface alive.
+    ///
+    /// The window backing a surface is dropped along with the last `Surface`, so anything that outlives the guest's
+    /// references, like an event loop, should hold one of these instead.
+    pub fn downgrade(&self) -> WeakSurface {
+        WeakSurface(Arc::downgrade(&self.0))
+    }
+
     pub fn pointer_up(&self, event: PointerEvent) {
+        self.flush_pointer_move(event.pointer_id);
         shared::unwrap_unless_inactive(self.0.pointer_up_sender.try_broadcast(event));
     }
     pub fn pointer_down(&self, event: PointerEvent) {
+        self.flush_pointer_move(event.pointer_id);
         shared::unwrap_unless_inactive(self.0.pointer_down_sender.try_broadcast(event));
     }
     pub fn pointer_move(&self, event: PointerEvent) {
-        shared::unwrap_unless_inactive_or_full(self.0.pointer_move_sender.try_broadcast(event));
+        send_coalesced(
+            &self.0.pointer_move_sender,
+            &self.0.pending_pointer_move,
+            event,
+        );
+    }
+    pub fn pointer_cancel(&self, event: PointerEvent) {
+        self.flush_pointer_move(event.pointer_id);
+        shared::unwrap_unless_inactive(self.0.pointer_cancel_sender.try_broadcast(event));
+    }
+    // sends the move held back for `pointer_id`, so the guest knows where the pointer was before it goes down, up, or is cancelled.
+    fn flush_pointer_move(&self, pointer_id: u64) {
+        let mut pending = self.0.pending_pointer_move.lock().unwrap();
+        if let Some(index) = pending
+            .iter()
+            .position(|event| event.pointer_id == pointer_id)
+        {
+            let event = pending.remove(index);
+            shared::unwrap_unless_inactive_or_full(self.0.pointer_move_sender.try_broadcast(event));
+        }
     }
     pub fn key_up(&self, event: KeyEvent) {
         shared::unwrap_unless_inactive(self.0.key_up_sender.try_broadcast(event));
@@ -114,12 +335,118 @@ impl Surface {
     pub fn canvas_resize(&self, event: ResizeEvent) {
         shared::unwrap_unless_inactive(self.0.resize_sender.try_broadcast(event));
     }
+    pub fn wheel(&self, event: WheelEvent) {
+        send_coalesced(&self.0.wheel_sender, &self.0.pending_wheel, event);
+    }
+    pub fn pinch(&self, event: PinchEvent) {
+        send_coalesced(&self.0.pinch_sender, &self.0.pending_pinch, event);
+    }
+    pub fn rotate(&self, event: RotateEvent) {
+        send_coalesced(&self.0.rotate_sender, &self.0.pending_rotate, event);
+    }
+    pub fn pointer_enter(&self, event: PointerEvent) {
+        shared::unwrap_unless_inactive(self.0.pointer_enter_sender.try_broadcast(event));
+    }
+    pub fn pointer_leave(&self, event: PointerEvent) {
+        shared::unwrap_unless_inactive(self.0.pointer_leave_sender.try_broadcast(event));
+    }
+    pub fn focus(&self, event: FocusEvent) {
+        shared::unwrap_unless_inactive(self.0.focus_sender.try_broadcast(event));
+    }
+    pub fn visibility_change(&self, event: VisibilityEvent) {
+        shared::unwrap_unless_inactive(self.0.visibility_sender.try_broadcast(event));
+    }
+    /// Send a close request. Returns false if the guest doesn't handle them, so the host has to close the window instead.
+    ///
+    /// A guest handles close requests once it has subscribed to them, whether or not it still reads the stream.
+    /// Requests it hasn't read within [`CLOSE_REQUEST_TIMEOUT`] aren't left to it anymore, the next one closes the window.
+    /// Once read, it's up to the guest whether to close, so a guest can ask the user to confirm.
+    pub fn close_requested(&self) -> bool {
+        self.close_requested_at(self.0.created.elapsed())
+    }
+    fn close_requested_at(&self, timestamp: Duration) -> bool {
+        let mut requests = self.0.close_requests.lock().unwrap();
+        let first = *requests.unread_since.get_or_insert(timestamp);
+        let event = CloseRequestedEvent {
+            timestamp: timestamp.as_nanos() as u64,
+        };
+        // sent while locked, so that a receiver can't clear `unread_since` before this request is in the channel.
+        shared::unwrap_unless_inactive(self.0.close_requested_sender.try_broadcast(event));
+        requests.subscribed && timestamp.saturating_sub(first) < CLOSE_REQUEST_TIMEOUT
+    }
+    fn close_requested_receiver(&self) -> CloseRequestedReceiver {
+        self.0.close_requests.lock().unwrap().subscribed = true;
+        CloseRequestedReceiver {
+            receiver: self.0.close_requested_sender.new_receiver(),
+            requests: Arc::clone(&self.0.close_requests),
+        }
+    }
+    /// Send a frame event, timestamped with the time since the surface was created.
     pub fn animation_frame(&self) {
-        shared::unwrap_unless_inactive_or_full(
-            self.0
-                .frame_sender
-                .try_broadcast(FrameEvent { nothing: true }),
-        );
+        self.animation_frame_at(self.0.created.elapsed());
+    }
+    /// Send a frame event with a timestamp from another clock, e.g. a virtual one.
+    ///
+    /// Timestamps earlier than the previous frame's are clamped, so that guests always see monotonic time.
+    pub fn animation_frame_at(&self, timestamp: Duration) {
+        // so that the guest sees merged events before the frame they were merged for.
+        flush_coalesced(&self.0.pointer_move_sender, &self.0.pending_pointer_move);
+        flush_coalesced(&self.0.wheel_sender, &self.0.pending_wheel);
+        flush_coalesced(&self.0.pinch_sender, &self.0.pending_pinch);
+        flush_coalesced(&self.0.rotate_sender, &self.0.pending_rotate);
+        let event = {
+            let mut clock = self.0.frame_clock.lock().unwrap();
+            let last = clock.last.unwrap_or(timestamp);
+            let timestamp = timestamp.max(last);
+            let event = FrameEvent {
+                timestamp: timestamp.as_nanos() as u64,
+                index: clock.index,
+                delta: (timestamp - last).as_nanos() as u64,
+            };
+            clock.index += 1;
+            clock.last = Some(timestamp);
+            event
+        };
+        shared::unwrap_unless_inactive_or_full(self.0.frame_sender.try_broadcast(event));
+    }
+    /// Number of frame events sent so far, including ones the guest missed.
+    pub fn frame_index(&self) -> u64 {
+        self.0.frame_clock.lock().unwrap().index
+    }
+    pub fn pre_present_notify(&self) {
+        self.0.window.pre_present_notify();
+    }
+
+    /// Wait until the guest received every animation frame sent so far. Returns false if it didn't within `timeout`.
+    #[cfg(feature = "input-script")]
+    pub(crate) fn wait_frame_received(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.0.frame_received.lock.lock().unwrap();
+        while !self.0.frame_sender.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            guard = self
+                .0
+                .frame_received
+                .condvar
+                .wait_timeout(guard, remaining)
+                .unwrap()
+                .0;
+        }
+        true
+    }
+}
+
+/// See [`Surface::downgrade`].
+#[derive(Clone, Debug)]
+pub struct WeakSurface(Weak<SurfaceInner>);
+
+impl WeakSurface {
+    /// `None` once the surface was dropped.
+    pub fn upgrade(&self) -> Option<Surface> {
+        self.0.upgrade().map(Surface)
     }
 }
 
@@ -149,6 +476,9 @@ struct SurfaceInner {
     _pointer_down_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
     pointer_move_sender: async_broadcast::Sender<PointerEvent>,
     _pointer_move_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
+    pending_pointer_move: Arc<Mutex<Vec<PointerEvent>>>,
+    pointer_cancel_sender: async_broadcast::Sender<PointerEvent>,
+    _pointer_cancel_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
     key_up_sender: async_broadcast::Sender<KeyEvent>,
     _key_up_receiver: async_broadcast::InactiveReceiver<KeyEvent>,
     key_down_sender: async_broadcast::Sender<KeyEvent>,
@@ -157,6 +487,281 @@ struct SurfaceInner {
     _resize_receiver: async_broadcast::InactiveReceiver<ResizeEvent>,
     frame_sender: async_broadcast::Sender<FrameEvent>,
     _frame_receiver: async_broadcast::InactiveReceiver<FrameEvent>,
+    // High frequency events are held back here while the guest hasn't read the previous one, see `send_coalesced`.
+    wheel_sender: async_broadcast::Sender<WheelEvent>,
+    _wheel_receiver: async_broadcast::InactiveReceiver<WheelEvent>,
+    pending_wheel: Arc<Mutex<Vec<WheelEvent>>>,
+    pinch_sender: async_broadcast::Sender<PinchEvent>,
+    _pinch_receiver: async_broadcast::InactiveReceiver<PinchEvent>,
+    pending_pinch: Arc<Mutex<Vec<PinchEvent>>>,
+    rotate_sender: async_broadcast::Sender<RotateEvent>,
+    _rotate_receiver: async_broadcast::InactiveReceiver<RotateEvent>,
+    pending_rotate: Arc<Mutex<Vec<RotateEvent>>>,
+    pointer_enter_sender: async_broadcast::Sender<PointerEvent>,
+    _pointer_enter_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
+    pointer_leave_sender: async_broadcast::Sender<PointerEvent>,
+    _pointer_leave_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
+    focus_sender: async_broadcast::Sender<FocusEvent>,
+    _focus_receiver: async_broadcast::InactiveReceiver<FocusEvent>,
+    visibility_sender: async_broadcast::Sender<VisibilityEvent>,
+    _visibility_receiver: async_broadcast::InactiveReceiver<VisibilityEvent>,
+    close_requested_sender: async_broadcast::Sender<CloseRequestedEvent>,
+    _close_requested_receiver: async_broadcast::InactiveReceiver<CloseRequestedEvent>,
+    close_requests: Arc<Mutex<CloseRequests>>,
+
+    created: Instant,
+    frame_clock: Mutex<FrameClock>,
+    frame_received: Arc<FrameReceived>,
+}
+
+/// How long a guest that handles close requests has to read one before the host closes the window on the next.
+pub const CLOSE_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Default)]
+struct CloseRequests {
+    // set once the guest subscribes, and never unset.
+    subscribed: bool,
+    // when the oldest request the guest hasn't read yet was sent.
+    unread_since: Option<Duration>,
+}
+
+/// Receiver for close requests, that tells [`Surface::close_requested`] once the guest read them.
+struct CloseRequestedReceiver {
+    receiver: async_broadcast::Receiver<CloseRequestedEvent>,
+    requests: Arc<Mutex<CloseRequests>>,
+}
+
+impl Stream for CloseRequestedReceiver {
+    type Item = CloseRequestedEvent;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<CloseRequestedEvent>> {
+        let poll = self.receiver.poll_next_unpin(cx);
+        if let Poll::Ready(Some(_)) = poll {
+            let mut requests = self.requests.lock().unwrap();
+            if self.receiver.is_empty() {
+                requests.unread_since = None;
+            }
+        }
+        poll
+    }
+}
+
+/// Events that can be merged into one, e.g. adding up scroll deltas.
+trait Coalesce: Sized {
+    /// Merge `next` into `self`, or return false if they can't be merged.
+    fn coalesce(&mut self, next: &Self) -> bool;
+
+    /// Whether `next` comes from the same source as `self`. Events of different sources are held back separately.
+    fn same_source(&self, _next: &Self) -> bool {
+        true
+    }
+}
+
+// moves of different pointers, e.g. two fingers, are merged separately.
+impl Coalesce for PointerEvent {
+    fn coalesce(&mut self, next: &Self) -> bool {
+        *self = *next;
+        true
+    }
+
+    fn same_source(&self, next: &Self) -> bool {
+        self.pointer_id == next.pointer_id
+    }
+}
+
+impl Coalesce for WheelEvent {
+    fn coalesce(&mut self, next: &Self) -> bool {
+        let same_modifiers = (self.alt_key, self.ctrl_key, self.meta_key, self.shift_key)
+            == (next.alt_key, next.ctrl_key, next.meta_key, next.shift_key);
+        if self.delta_mode != next.delta_mode || !same_modifiers {
+            return false;
+        }
+        self.x = next.x;
+        self.y = next.y;
+        self.delta_x += next.delta_x;
+        self.delta_y += next.delta_y;
+        self.delta_z += next.delta_z;
+        true
+    }
+}
+
+// only moves are merged, so that the guest sees every gesture start and end.
+fn gesture_coalesces(phase: GesturePhase, next: GesturePhase) -> bool {
+    matches!(phase, GesturePhase::Started | GesturePhase::Moved) && next == GesturePhase::Moved
+}
+
+impl Coalesce for PinchEvent {
+    fn coalesce(&mut self, next: &Self) -> bool {
+        if !gesture_coalesces(self.phase, next.phase) {
+            return false;
+        }
+        self.x = next.x;
+        self.y = next.y;
+        // scales multiply.
+        self.delta = (1.0 + self.delta) * (1.0 + next.delta) - 1.0;
+        true
+    }
+}
+
+impl Coalesce for RotateEvent {
+    fn coalesce(&mut self, next: &Self) -> bool {
+        if !gesture_coalesces(self.phase, next.phase) {
+            return false;
+        }
+        self.x = next.x;
+        self.y = next.y;
+        self.delta += next.delta;
+        true
+    }
+}
+
+/// Send `event` right away if the guest read everything sent so far, otherwise merge it into the event held back for its source.
+/// High frequency devices like trackpads then can't flood the guest, and no deltas are lost.
+fn send_coalesced<T: Coalesce + Clone>(
+    sender: &async_broadcast::Sender<T>,
+    pending: &Mutex<Vec<T>>,
+    event: T,
+) {
+    let mut pending = pending.lock().unwrap();
+    let event = match pending.iter().position(|held| held.same_source(&event)) {
+        Some(index) => {
+            let mut merged = pending.remove(index);
+            if merged.coalesce(&event) {
+                merged
+            } else {
+                shared::unwrap_unless_inactive_or_full(sender.try_broadcast(merged));
+                event
+            }
+        }
+        None => event,
+    };
+    if sender.is_empty() {
+        shared::unwrap_unless_inactive_or_full(sender.try_broadcast(event));
+    } else {
+        pending.push(event);
+    }
+}
+
+/// Send the events held back by [`send_coalesced`], if the guest is ready for them.
+fn flush_coalesced<T: Clone>(sender: &async_broadcast::Sender<T>, pending: &Mutex<Vec<T>>) {
+    let mut pending = pending.lock().unwrap();
+    if sender.is_empty() {
+        for event in pending.drain(..) {
+            shared::unwrap_unless_inactive_or_full(sender.try_broadcast(event));
+        }
+    }
+}
+
+/// Receiver for a channel fed by [`send_coalesced`].
+///
+/// Sends the held back event as soon as every receiver has read what was sent before it,
+/// rather than waiting for the next frame, which doesn't come while frames are paused.
+struct CoalescedReceiver<T> {
+    receiver: async_broadcast::Receiver<T>,
+    sender: async_broadcast::Sender<T>,
+    pending: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T: Clone> CoalescedReceiver<T> {
+    fn new(sender: &async_broadcast::Sender<T>, pending: &Arc<Mutex<Vec<T>>>) -> Self {
+        Self {
+            receiver: sender.new_receiver(),
+            sender: sender.clone(),
+            pending: Arc::clone(pending),
+        }
+    }
+}
+
+impl<T: Clone> Stream for CoalescedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.receiver.poll_next_unpin(cx) {
+            Poll::Pending => {
+                flush_coalesced(&self.sender, &self.pending);
+                self.receiver.poll_next_unpin(cx)
+            }
+            ready => ready,
+        }
+    }
+}
+
+/// Wakes up [`Surface::wait_frame_received`] whenever a frame receiver reads a frame, or goes away.
+#[derive(Debug, Default)]
+struct FrameReceived {
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl FrameReceived {
+    fn notify(&self) {
+        // taking the lock, so the waiter can't miss this between checking for pending frames and waiting.
+        let _guard = self.lock.lock().unwrap();
+        self.condvar.notify_all();
+    }
+}
+
+/// Receiver for animation frames, that notifies [`FrameReceived`].
+struct FrameReceiver {
+    // only `None` while dropping, so the frame counts as received before the waiter is woken up.
+    receiver: Option<async_broadcast::Receiver<FrameEvent>>,
+    received: Arc<FrameReceived>,
+}
+
+impl FrameReceiver {
+    fn new(surface: &SurfaceInner) -> Self {
+        Self {
+            receiver: Some(surface.frame_sender.new_receiver()),
+            received: Arc::clone(&surface.frame_received),
+        }
+    }
+}
+
+impl Stream for FrameReceiver {
+    type Item = FrameEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<FrameEvent>> {
+        let poll = self.receiver.as_mut().unwrap().poll_next_unpin(cx);
+        if let Poll::Ready(Some(_)) = poll {
+            self.received.notify();
+        }
+        poll
+    }
+}
+
+impl Drop for FrameReceiver {
+    fn drop(&mut self) {
+        self.receiver.take();
+        self.received.notify();
+    }
+}
+
+/// `PointerEvent::pointer_id` of a touch, from the platform's id for it. Starts at 1 to stay clear of the mouse.
+#[cfg(any(feature = "winit", feature = "input-script"))]
+pub(crate) fn touch_pointer_id(touch_id: u64) -> u64 {
+    touch_id.wrapping_add(1)
+}
+
+// https://www.w3.org/TR/uievents/#dom-mouseevent-buttons
+#[cfg(any(feature = "winit", feature = "input-script"))]
+pub(crate) fn pointer_button_mask(button: PointerButton) -> u16 {
+    match button {
+        PointerButton::Primary => 1,
+        PointerButton::Secondary => 2,
+        PointerButton::Auxiliary => 4,
+        PointerButton::Back => 8,
+        PointerButton::Forward => 16,
+    }
+}
+
+#[derive(Debug)]
+struct FrameClock {
+    index: u64,
+    // timestamp of the previous frame.
+    last: Option<Duration>,
 }
 
 impl Debug for SurfaceInner {
@@ -169,6 +774,9 @@ impl Debug for SurfaceInner {
             .field("_pointer_down_receiver", &self._pointer_down_receiver)
             .field("pointer_move_sender", &self.pointer_move_sender)
             .field("_pointer_move_receiver", &self._pointer_move_receiver)
+            .field("pending_pointer_move", &self.pending_pointer_move)
+            .field("pointer_cancel_sender", &self.pointer_cancel_sender)
+            .field("_pointer_cancel_receiver", &self._pointer_cancel_receiver)
             .field("key_up_sender", &self.key_up_sender)
             .field("_key_up_receiver", &self._key_up_receiver)
             .field("key_down_sender", &self.key_down_sender)
@@ -177,6 +785,29 @@ impl Debug for SurfaceInner {
             .field("_resize_receiver", &self._resize_receiver)
             .field("frame_sender", &self.frame_sender)
             .field("_frame_receiver", &self._frame_receiver)
+            .field("wheel_sender", &self.wheel_sender)
+            .field("_wheel_receiver", &self._wheel_receiver)
+            .field("pending_wheel", &self.pending_wheel)
+            .field("pinch_sender", &self.pinch_sender)
+            .field("_pinch_receiver", &self._pinch_receiver)
+            .field("pending_pinch", &self.pending_pinch)
+            .field("rotate_sender", &self.rotate_sender)
+            .field("_rotate_receiver", &self._rotate_receiver)
+            .field("pending_rotate", &self.pending_rotate)
+            .field("pointer_enter_sender", &self.pointer_enter_sender)
+            .field("_pointer_enter_receiver", &self._pointer_enter_receiver)
+            .field("pointer_leave_sender", &self.pointer_leave_sender)
+            .field("_pointer_leave_receiver", &self._pointer_leave_receiver)
+            .field("focus_sender", &self.focus_sender)
+            .field("_focus_receiver", &self._focus_receiver)
+            .field("visibility_sender", &self.visibility_sender)
+            .field("_visibility_receiver", &self._visibility_receiver)
+            .field("close_requested_sender", &self.close_requested_sender)
+            .field("_close_requested_receiver", &self._close_requested_receiver)
+            .field("close_requests", &self.close_requests)
+            .field("created", &self.created)
+            .field("frame_clock", &self.frame_clock)
+            .field("frame_received", &self.frame_received)
             .finish()
     }
 }
@@ -187,6 +818,10 @@ where
     T: SurfaceCtxView,
 {
     wasi_gfx::surface::surface::add_to_linker::<_, HasSurfaceCtx<T::Spawner>>(l, T::surface_ctx)?;
+    wasi_gfx::surface_ext::surface_ext::add_to_linker::<_, HasSurfaceCtx<T::Spawner>>(
+        l,
+        T::surface_ctx,
+    )?;
     Ok(())
 }
 
@@ -226,9 +861,19 @@ pub trait MainThreadSpawner: Send + Sync + 'static {
 impl<'a, S: MainThreadSpawner> surface::Host for SurfaceCtx<'a, S> {}
 
 impl<'a, S: MainThreadSpawner> surface::HostSurface for SurfaceCtx<'a, S> {
-    fn new(&mut self, desc: SurfaceDesc) -> wasmtime::Result<Resource<Surface>> {
-        let surface = futures::executor::block_on(self.main_thread_spawner.create_surface(desc));
-        Ok(self.table.push(surface)?)
+    fn new(&mut self, desc: surface::CreateDesc) -> wasmtime::Result<Resource<Surface>> {
+        surface_ext::Host::create_surface(
+            self,
+            SurfaceDesc {
+                height: desc.height,
+                width: desc.width,
+                title: None,
+                resizable: None,
+                decorations: None,
+                fullscreen: None,
+                visible: None,
+            },
+        )
     }
 
     fn height(&mut self, surface: Resource<Surface>) -> wasmtime::Result<u32> {
@@ -262,31 +907,34 @@ impl<T, S: MainThreadSpawner> surface::HostSurfaceWithStore<T> for HasSurfaceCtx
     fn on_pointer_down(
         mut access: Access<T, Self>,
         surface: Resource<surface::Surface>,
-    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+    ) -> wasmtime::Result<StreamReader<surface::PointerEvent>> {
         let ctx = access.get();
         let surface = ctx.table.get(&surface)?;
         let receiver = surface.0.pointer_down_sender.new_receiver();
-        Ok(channel_to_stream(access, receiver))
+        StreamReader::new(access, StreamPipe(receiver.map(base_pointer_event)))
     }
 
     fn on_pointer_move(
         mut access: Access<T, Self>,
         surface: Resource<surface::Surface>,
-    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+    ) -> wasmtime::Result<StreamReader<surface::PointerEvent>> {
         let ctx = access.get();
         let surface = ctx.table.get(&surface)?;
-        let receiver = surface.0.pointer_move_sender.new_receiver();
-        Ok(channel_to_stream(access, receiver))
+        let receiver = CoalescedReceiver::new(
+            &surface.0.pointer_move_sender,
+            &surface.0.pending_pointer_move,
+        );
+        StreamReader::new(access, StreamPipe(receiver.map(base_pointer_event)))
     }
 
     fn on_pointer_up(
         mut access: Access<T, Self>,
         surface: Resource<surface::Surface>,
-    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+    ) -> wasmtime::Result<StreamReader<surface::PointerEvent>> {
         let ctx = access.get();
         let surface = ctx.table.get(&surface)?;
         let receiver = surface.0.pointer_up_sender.new_receiver();
-        Ok(channel_to_stream(access, receiver))
+        StreamReader::new(access, StreamPipe(receiver.map(base_pointer_event)))
     }
 
     fn on_key_up(
@@ -322,10 +970,510 @@ impl<T, S: MainThreadSpawner> surface::HostSurfaceWithStore<T> for HasSurfaceCtx
     fn on_frame(
         mut access: Access<T, Self>,
         surface: Resource<surface::Surface>,
+    ) -> wasmtime::Result<StreamReader<surface::FrameEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = FrameReceiver::new(&surface.0);
+        StreamReader::new(access, StreamPipe(receiver.map(base_frame_event)))
+    }
+}
+
+// `wasi-gfx:surface` events carry less than the ones sent to `wasi-gfx:surface-ext` streams.
+fn base_pointer_event(event: PointerEvent) -> surface::PointerEvent {
+    surface::PointerEvent {
+        x: event.x,
+        y: event.y,
+    }
+}
+
+fn base_frame_event(_event: FrameEvent) -> surface::FrameEvent {
+    surface::FrameEvent { nothing: true }
+}
+
+impl<'a, S: MainThreadSpawner> surface_ext::Host for SurfaceCtx<'a, S> {
+    fn create_surface(&mut self, desc: SurfaceDesc) -> wasmtime::Result<Resource<Surface>> {
+        let surface = futures::executor::block_on(self.main_thread_spawner.create_surface(desc));
+        Ok(self.table.push(surface)?)
+    }
+
+    fn set_title(&mut self, surface: Resource<Surface>, title: String) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_title(&title);
+        Ok(())
+    }
+
+    fn fullscreen(&mut self, surface: Resource<Surface>) -> wasmtime::Result<bool> {
+        let surface = self.table.get(&surface)?;
+        Ok(surface.fullscreen())
+    }
+
+    fn set_fullscreen(
+        &mut self,
+        surface: Resource<Surface>,
+        fullscreen: bool,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_fullscreen(fullscreen);
+        Ok(())
+    }
+
+    fn set_visible(&mut self, surface: Resource<Surface>, visible: bool) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_visible(visible);
+        Ok(())
+    }
+
+    fn set_resizable(
+        &mut self,
+        surface: Resource<Surface>,
+        resizable: bool,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_resizable(resizable);
+        Ok(())
+    }
+
+    fn set_decorations(
+        &mut self,
+        surface: Resource<Surface>,
+        decorations: bool,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_decorations(decorations);
+        Ok(())
+    }
+
+    fn set_min_size(
+        &mut self,
+        surface: Resource<Surface>,
+        size: Option<WindowSize>,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_min_size(size);
+        Ok(())
+    }
+
+    fn set_max_size(
+        &mut self,
+        surface: Resource<Surface>,
+        size: Option<WindowSize>,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_max_size(size);
+        Ok(())
+    }
+
+    fn set_cursor_visible(
+        &mut self,
+        surface: Resource<Surface>,
+        visible: bool,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_cursor_visible(visible);
+        Ok(())
+    }
+
+    fn set_cursor_grab(
+        &mut self,
+        surface: Resource<Surface>,
+        grab: CursorGrab,
+    ) -> wasmtime::Result<CursorGrab> {
+        let surface = self.table.get(&surface)?;
+        Ok(surface.set_cursor_grab(grab))
+    }
+
+    fn set_cursor_icon(
+        &mut self,
+        surface: Resource<Surface>,
+        icon: CursorIcon,
+    ) -> wasmtime::Result<()> {
+        let surface = self.table.get(&surface)?;
+        surface.set_cursor_icon(icon);
+        Ok(())
+    }
+}
+
+impl<T, S: MainThreadSpawner> surface_ext::HostWithStore<T> for HasSurfaceCtx<S> {
+    fn on_frame(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
     ) -> wasmtime::Result<StreamReader<FrameEvent>> {
         let ctx = access.get();
         let surface = ctx.table.get(&surface)?;
-        let receiver = surface.0.frame_sender.new_receiver();
+        let receiver = FrameReceiver::new(&surface.0);
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+
+    fn on_pointer_up(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.pointer_up_sender.new_receiver();
         Ok(channel_to_stream(access, receiver))
     }
+
+    fn on_pointer_down(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.pointer_down_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_pointer_move(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = CoalescedReceiver::new(
+            &surface.0.pointer_move_sender,
+            &surface.0.pending_pointer_move,
+        );
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+
+    fn on_pointer_cancel(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.pointer_cancel_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_wheel(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<WheelEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = CoalescedReceiver::new(&surface.0.wheel_sender, &surface.0.pending_wheel);
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+
+    fn on_pinch(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PinchEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = CoalescedReceiver::new(&surface.0.pinch_sender, &surface.0.pending_pinch);
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+
+    fn on_rotate(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<RotateEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = CoalescedReceiver::new(&surface.0.rotate_sender, &surface.0.pending_rotate);
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+
+    fn on_pointer_enter(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.pointer_enter_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_pointer_leave(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<PointerEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.pointer_leave_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_focus(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<FocusEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.focus_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_visibility_change(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<VisibilityEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.0.visibility_sender.new_receiver();
+        Ok(channel_to_stream(access, receiver))
+    }
+
+    fn on_close_requested(
+        mut access: Access<T, Self>,
+        surface: Resource<Surface>,
+    ) -> wasmtime::Result<StreamReader<CloseRequestedEvent>> {
+        let ctx = access.get();
+        let surface = ctx.table.get(&surface)?;
+        let receiver = surface.close_requested_receiver();
+        StreamReader::new(access, StreamPipe(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{test_surface, HeadlessSpawner};
+    use futures::FutureExt;
+
+    fn pointer_move(pointer_id: u64, x: f64) -> PointerEvent {
+        PointerEvent {
+            x,
+            y: 0.0,
+            pointer_id,
+            pointer_type: PointerType::Touch,
+            button: None,
+            buttons: 1,
+            pressure: 0.5,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            alt_key: false,
+            ctrl_key: false,
+            meta_key: false,
+            shift_key: false,
+        }
+    }
+
+    fn received<T: Clone>(receiver: &mut async_broadcast::Receiver<T>) -> Vec<T> {
+        std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+    }
+
+    // timestamps of the requests the guest reads, the way its stream would.
+    fn close_requests_read(requests: &mut CloseRequestedReceiver) -> Vec<Duration> {
+        std::iter::from_fn(|| requests.next().now_or_never().flatten())
+            .map(|e| Duration::from_nanos(e.timestamp))
+            .collect()
+    }
+
+    #[test]
+    fn pointer_moves_coalesce_per_finger() {
+        let surface = test_surface(&HeadlessSpawner::new());
+        let mut moves = surface.0.pointer_move_sender.new_receiver();
+        let mut ups = surface.0.pointer_up_sender.new_receiver();
+
+        // the first move is sent right away, the rest wait for the guest.
+        surface.pointer_move(pointer_move(1, 1.0));
+        surface.pointer_move(pointer_move(1, 2.0));
+        surface.pointer_move(pointer_move(1, 3.0));
+        // a move of another finger is held back separately.
+        surface.pointer_move(pointer_move(2, 10.0));
+        // the other finger's move doesn't wait for the first one to lift.
+        surface.pointer_up(pointer_move(1, 3.0));
+        assert_eq!(
+            received(&mut moves)
+                .iter()
+                .map(|e| (e.pointer_id, e.x))
+                .collect::<Vec<_>>(),
+            [(1, 1.0), (1, 3.0)]
+        );
+        assert_eq!(received(&mut ups).len(), 1);
+
+        // lifting a finger sends its held back move first.
+        surface.pointer_move(pointer_move(2, 11.0));
+        surface.pointer_up(pointer_move(2, 11.0));
+        assert_eq!(
+            received(&mut moves)
+                .iter()
+                .map(|e| (e.pointer_id, e.x))
+                .collect::<Vec<_>>(),
+            [(2, 11.0)]
+        );
+        assert!(surface.0.pending_pointer_move.lock().unwrap().is_empty());
+
+        // alternating fingers, more moves than the channel holds, while the guest isn't reading.
+        surface.pointer_move(pointer_move(3, 0.0));
+        for x in 1..=20 {
+            surface.pointer_move(pointer_move(3 + x as u64 % 2, x as f64));
+        }
+        assert_eq!(received(&mut moves).len(), 1);
+        // the guest read everything, so the frame sends each finger's last position.
+        surface.animation_frame();
+        assert_eq!(
+            received(&mut moves)
+                .iter()
+                .map(|e| (e.pointer_id, e.x))
+                .collect::<Vec<_>>(),
+            [(4, 19.0), (3, 20.0)]
+        );
+    }
+
+    #[test]
+    fn close_requests() {
+        // a guest that never subscribed leaves closing to the host.
+        let surface = test_surface(&HeadlessSpawner::new());
+        assert!(!surface.close_requested());
+
+        // one that subscribed keeps handling them after dropping the stream.
+        let surface = test_surface(&HeadlessSpawner::new());
+        drop(surface.close_requested_receiver());
+        let mut requests = surface.close_requested_receiver();
+        let start = Duration::from_secs(1);
+        assert!(surface.close_requested_at(start));
+        assert!(surface.close_requested_at(start + CLOSE_REQUEST_TIMEOUT / 2));
+        // until it leaves one unread for too long.
+        assert!(!surface.close_requested_at(start + CLOSE_REQUEST_TIMEOUT));
+        assert_eq!(
+            close_requests_read(&mut requests),
+            [
+                start,
+                start + CLOSE_REQUEST_TIMEOUT / 2,
+                start + CLOSE_REQUEST_TIMEOUT
+            ]
+        );
+    }
+
+    #[test]
+    fn read_close_requests_stay_with_the_guest() {
+        let surface = test_surface(&HeadlessSpawner::new());
+        let mut requests = surface.close_requested_receiver();
+        let start = Duration::from_secs(1);
+
+        // the guest reads the request and decides not to close, e.g. the user cancelled.
+        assert!(surface.close_requested_at(start));
+        assert_eq!(close_requests_read(&mut requests), [start]);
+        let later = start + CLOSE_REQUEST_TIMEOUT * 2;
+        assert!(surface.close_requested_at(later));
+        assert_eq!(close_requests_read(&mut requests), [later]);
+        assert!(surface.close_requested_at(later + CLOSE_REQUEST_TIMEOUT * 2));
+    }
+
+    #[cfg(feature = "input-script")]
+    #[test]
+    fn window_script_events() {
+        use crate::input_script::{InputEvent, InputScript};
+
+        let surface = test_surface(&HeadlessSpawner::new());
+        let mut focus = surface.0.focus_sender.new_receiver();
+        let mut visibility = surface.0.visibility_sender.new_receiver();
+        let mut close_requests = surface.close_requested_receiver();
+
+        InputScript {
+            events: vec![
+                InputEvent::Focus(FocusEvent { focused: false }),
+                InputEvent::VisibilityChange(VisibilityEvent { visible: false }),
+                InputEvent::VisibilityChange(VisibilityEvent { visible: true }),
+                InputEvent::CloseRequested,
+            ],
+        }
+        .play(&surface, Duration::from_secs(1))
+        .unwrap();
+
+        assert_eq!(
+            received(&mut focus)
+                .iter()
+                .map(|e| e.focused)
+                .collect::<Vec<_>>(),
+            [false]
+        );
+        assert_eq!(
+            received(&mut visibility)
+                .iter()
+                .map(|e| e.visible)
+                .collect::<Vec<_>>(),
+            [false, true]
+        );
+        assert_eq!(close_requests_read(&mut close_requests).len(), 1);
+        // still open, the guest decides.
+        assert!(surface.headless().is_some());
+    }
+
+    #[cfg(feature = "input-script")]
+    #[test]
+    fn touch_script_events() {
+        use crate::input_script::{InputEvent, InputScript, TouchPoint};
+
+        let surface = test_surface(&HeadlessSpawner::new());
+        let mut downs = surface.0.pointer_down_sender.new_receiver();
+        let mut moves = surface.0.pointer_move_sender.new_receiver();
+        let mut ups = surface.0.pointer_up_sender.new_receiver();
+        let mut cancels = surface.0.pointer_cancel_sender.new_receiver();
+
+        let touch = |id, x| TouchPoint { id, x, y: 5.0 };
+        InputScript {
+            events: vec![
+                InputEvent::TouchStart(touch(0, 1.0)),
+                InputEvent::TouchStart(touch(1, 10.0)),
+                InputEvent::TouchMove(touch(0, 2.0)),
+                InputEvent::TouchMove(touch(0, 3.0)),
+                InputEvent::TouchEnd(touch(0, 3.0)),
+                InputEvent::TouchCancel(touch(1, 10.0)),
+            ],
+        }
+        .play(&surface, Duration::from_secs(1))
+        .unwrap();
+
+        let downs = received(&mut downs);
+        assert_eq!(
+            downs.iter().map(|e| e.pointer_id).collect::<Vec<_>>(),
+            [touch_pointer_id(0), touch_pointer_id(1)]
+        );
+        assert!(downs.iter().all(|e| e.pointer_type == PointerType::Touch
+            && e.button == Some(PointerButton::Primary)
+            && e.buttons == 1));
+        // the held back move is sent before the finger lifts.
+        assert_eq!(
+            received(&mut moves)
+                .iter()
+                .map(|e| (e.pointer_id, e.x))
+                .collect::<Vec<_>>(),
+            [(touch_pointer_id(0), 2.0), (touch_pointer_id(0), 3.0)]
+        );
+        let ups = received(&mut ups);
+        assert_eq!(ups.len(), 1);
+        assert_eq!(ups[0].pointer_id, touch_pointer_id(0));
+        assert_eq!((ups[0].buttons, ups[0].pressure), (0, 0.0));
+        let cancels = received(&mut cancels);
+        assert_eq!(cancels.len(), 1);
+        assert_eq!(cancels[0].pointer_id, touch_pointer_id(1));
+    }
+
+    #[cfg(feature = "input-script")]
+    #[test]
+    fn play_waits_for_frames() {
+        use crate::input_script::{InputEvent, InputScript, InputScriptError};
+
+        let surface = test_surface(&HeadlessSpawner::new());
+        let script = InputScript {
+            events: vec![InputEvent::Frame, InputEvent::Frame],
+        };
+        // nobody listening for frames, nothing to wait for.
+        script.play(&surface, Duration::from_secs(1)).unwrap();
+
+        // a guest that reads every frame.
+        let mut frames = FrameReceiver::new(&surface.0);
+        let guest = std::thread::spawn(move || {
+            futures::executor::block_on(async {
+                for _ in 0..2 {
+                    frames.next().await.unwrap();
+                }
+            })
+        });
+        script.play(&surface, Duration::from_secs(10)).unwrap();
+        guest.join().unwrap();
+
+        // a guest that stopped reading frames.
+        let _frames = FrameReceiver::new(&surface.0);
+        assert!(matches!(
+            script.play(&surface, Duration::from_millis(10)),
+            Err(InputScriptError::FrameTimeout { frame: 0 })
+        ));
+    }
 }
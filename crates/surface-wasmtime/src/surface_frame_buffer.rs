@@ -1,4 +1,4 @@
-use crate::surface::{MainThreadSpawner, Surface};
+use crate::surface::{MainThreadSpawner, PresentedFrame, Surface};
 use frame_buffer_wasmtime::{GfxBuffer, HasBuffer};
 use std::{
     marker::PhantomData,
@@ -23,26 +23,28 @@ wasmtime::component::bindgen!({
 
 // types
 pub struct GfxContext {
+    gfx_surface: Surface,
     fb_surface: FBSurfaceArc,
 }
 
 struct FBSurfaceArc {
-    surface: Arc<Mutex<softbuffer::Surface<Surface, Surface>>>,
+    /// `None` for headless surfaces, which present into memory instead.
+    surface: Option<Arc<Mutex<softbuffer::Surface<Surface, Surface>>>>,
     /// softbuffer only presents pixels written into the current `buffer_mut()`, so
     /// `set_buffer` stashes the frame here and `present` copies + presents it.
     staged: Arc<Mutex<Vec<u32>>>,
 }
 
 impl FBSurfaceArc {
-    pub fn new(surface: softbuffer::Surface<Surface, Surface>) -> Self {
+    pub fn new(surface: Option<softbuffer::Surface<Surface, Surface>>) -> Self {
         Self {
-            surface: Arc::new(Mutex::new(surface)),
+            surface: surface.map(|surface| Arc::new(Mutex::new(surface))),
             staged: Arc::new(Mutex::new(Vec::new())),
         }
     }
     pub fn arc_clone(&self) -> Self {
         Self {
-            surface: Arc::clone(&self.surface),
+            surface: self.surface.as_ref().map(Arc::clone),
             staged: Arc::clone(&self.staged),
         }
     }
@@ -105,28 +107,41 @@ impl<'a, S: MainThreadSpawner> surface_frame_buffer::HostContext for SurfaceFram
     ) -> wasmtime::Result<Resource<surface_frame_buffer::Context>> {
         let gfx_surface = self.table.get(&gfx_surface)?.arc_clone();
 
-        let fb_surface = futures::executor::block_on(self.main_thread_spawner.spawn(move || {
-            let fb_context = softbuffer::Context::new(gfx_surface.arc_clone()).unwrap();
-            let mut fb_surface =
-                softbuffer::Surface::new(&fb_context, gfx_surface.arc_clone()).unwrap();
-
-            fb_surface
-                .resize(
-                    gfx_surface
-                        .width()
-                        .try_into()
-                        .unwrap_or(NonZeroU32::new(1).unwrap()),
-                    gfx_surface
-                        .height()
-                        .try_into()
-                        .unwrap_or(NonZeroU32::new(1).unwrap()),
-                )
-                .unwrap();
-
-            FBSurfaceArc::new(fb_surface)
+        if gfx_surface.frame_sink().is_some() {
+            return Ok(self.table.push(GfxContext {
+                gfx_surface,
+                fb_surface: FBSurfaceArc::new(None),
+            })?);
+        }
+
+        let fb_surface = futures::executor::block_on(self.main_thread_spawner.spawn({
+            let gfx_surface = gfx_surface.arc_clone();
+            move || {
+                let fb_context = softbuffer::Context::new(gfx_surface.arc_clone()).unwrap();
+                let mut fb_surface =
+                    softbuffer::Surface::new(&fb_context, gfx_surface.arc_clone()).unwrap();
+
+                fb_surface
+                    .resize(
+                        gfx_surface
+                            .width()
+                            .try_into()
+                            .unwrap_or(NonZeroU32::new(1).unwrap()),
+                        gfx_surface
+                            .height()
+                            .try_into()
+                            .unwrap_or(NonZeroU32::new(1).unwrap()),
+                    )
+                    .unwrap();
+
+                FBSurfaceArc::new(Some(fb_surface))
+            }
         }));
 
-        let gfx_context = GfxContext { fb_surface };
+        let gfx_context = GfxContext {
+            gfx_surface,
+            fb_surface,
+        };
         Ok(self.table.push(gfx_context)?)
     }
 
@@ -147,15 +162,29 @@ impl<'a, S: MainThreadSpawner> surface_frame_buffer::HostContext for SurfaceFram
     ) -> wasmtime::Result<()> {
         let gfx_context = self.table.get_mut(&gfx_context)?;
         let staged = gfx_context.fb_surface.staged.lock().unwrap();
-        let mut surface = gfx_context.fb_surface.surface.lock().unwrap();
+        let Some(surface) = &gfx_context.fb_surface.surface else {
+            let frame_sink = gfx_context.gfx_surface.frame_sink().unwrap();
+            frame_sink.present(PresentedFrame::FrameBuffer {
+                width: gfx_context.gfx_surface.width(),
+                height: gfx_context.gfx_surface.height(),
+                pixels: staged.clone(),
+            });
+            return Ok(());
+        };
+        let mut surface = surface.lock().unwrap();
         let mut buffer = surface.buffer_mut().unwrap();
         buffer.copy_from_slice(&staged);
+        gfx_context.gfx_surface.pre_present_notify();
         buffer.present().unwrap();
         Ok(())
     }
 
     fn drop(&mut self, context: Resource<surface_frame_buffer::Context>) -> wasmtime::Result<()> {
-        self.table.delete(context)?;
+        let context = self.table.delete(context)?;
+        // released where it was created. Buffers the guest still holds keep it alive until they're dropped.
+        if let Some(fb_surface) = context.fb_surface.surface {
+            futures::executor::block_on(self.main_thread_spawner.spawn(move || drop(fb_surface)));
+        }
         Ok(())
     }
 }
@@ -1,16 +1,19 @@
-use crate::surface::MainThreadSpawner;
+use crate::surface::{MainThreadSpawner, PresentedFrame};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use std::marker::PhantomData;
 use std::sync::Arc;
 use wasi_gfx::surface::surface_webgpu;
+use wasi_gfx::surface_ext::surface_webgpu_ext;
 use wasi_webgpu_wasmtime::reexports::{wgpu_core, wgpu_types};
+use wasi_webgpu_wasmtime::wasi::webgpu::webgpu;
+use wasi_webgpu_wasmtime::ValidationError;
 use wasmtime::{
     bail,
     component::{HasData, Resource},
 };
 
 wasmtime::component::bindgen!({
-    world: "wasi-gfx:surface/webgpu-imports",
+    world: "wasi-gfx:surface-ext/webgpu-imports",
     require_store_data_send: true,
     imports: {
         default: trappable,
@@ -25,12 +28,452 @@ wasmtime::component::bindgen!({
 // types
 pub struct Context {
     pub(crate) surface: surface_webgpu::Surface,
-    pub(crate) surface_id: wgpu_core::id::SurfaceId,
-    pub(crate) configuration: Option<ContextConfiguration>,
+    pub(crate) target: ContextTarget,
+}
+
+pub(crate) enum ContextTarget {
+    Window {
+        surface_id: wgpu_core::id::SurfaceId,
+        configuration: Option<ContextConfiguration>,
+    },
+    // headless surfaces have no window to present to, so they render into an offscreen canvas instead.
+    Offscreen(wasi_webgpu_wasmtime::CanvasContext),
 }
 
 pub(crate) struct ContextConfiguration {
-    device: Resource<wasi_webgpu_wasmtime::Device>,
+    // a clone of the guest's device, since the borrow passed to `configure` doesn't outlive the call.
+    device: wasi_webgpu_wasmtime::Device,
+    config: wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>,
+    // what the surface is actually presented as, see `output_color`.
+    color_space: webgpu::PredefinedColorSpace,
+    tone_mapping_mode: webgpu::GpuCanvasToneMappingMode,
+    // the current texture is a stand-in for a frame the surface couldn't give, so there's nothing to present.
+    skip_present: bool,
+}
+
+impl Context {
+    /// The configuration the surface is currently configured with, if any.
+    pub fn configuration(
+        &self,
+    ) -> Option<wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>> {
+        match &self.target {
+            ContextTarget::Window { configuration, .. } => {
+                configuration.as_ref().map(|c| c.config.clone())
+            }
+            ContextTarget::Offscreen(canvas) => {
+                let configuration = canvas.configuration()?;
+                Some(wgpu_types::SurfaceConfiguration {
+                    usage: configuration
+                        .usage
+                        .unwrap_or(webgpu::GpuTextureUsage::RENDER_ATTACHMENT)
+                        .try_into()
+                        .ok()?,
+                    format: configuration.format.into(),
+                    width: canvas.width(),
+                    height: canvas.height(),
+                    // offscreen frames are read back one at a time.
+                    present_mode: wgpu_types::PresentMode::Fifo,
+                    desired_maximum_frame_latency: 1,
+                    alpha_mode: configuration
+                        .alpha_mode
+                        .unwrap_or(webgpu::GpuCanvasAlphaMode::Opaque)
+                        .into(),
+                    view_formats: configuration
+                        .view_formats
+                        .iter()
+                        .flatten()
+                        .map(|&f| f.into())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    /// The color space and tone mapping the surface is presented with, if configured.
+    ///
+    /// These can differ from what the guest asked for, when the surface can't present it.
+    pub fn output_color(
+        &self,
+    ) -> Option<(
+        webgpu::PredefinedColorSpace,
+        webgpu::GpuCanvasToneMappingMode,
+    )> {
+        match &self.target {
+            ContextTarget::Window { configuration, .. } => configuration
+                .as_ref()
+                .map(|c| (c.color_space, c.tone_mapping_mode)),
+            ContextTarget::Offscreen(canvas) => canvas
+                .configuration()
+                .map(|c| output_color(c.format.into(), c.color_space, c.tone_mapping)),
+        }
+    }
+
+    /// What the surface supports when rendered to with `device`.
+    pub fn capabilities(
+        &self,
+        instance: &wgpu_core::global::Global,
+        device: &wasi_webgpu_wasmtime::Device,
+    ) -> Result<wgpu_types::SurfaceCapabilities, wgpu_core::instance::GetSurfaceSupportError> {
+        match &self.target {
+            ContextTarget::Window { surface_id, .. } => {
+                instance.surface_get_capabilities(*surface_id, *device.adapter_id())
+            }
+            ContextTarget::Offscreen(_) => Ok(wgpu_types::SurfaceCapabilities {
+                formats: wasi_webgpu_wasmtime::SUPPORTED_CONTEXT_FORMATS.to_vec(),
+                present_modes: vec![wgpu_types::PresentMode::Fifo],
+                alpha_modes: vec![
+                    wgpu_types::CompositeAlphaMode::Opaque,
+                    wgpu_types::CompositeAlphaMode::PreMultiplied,
+                ],
+                usages: WEBGPU_TEXTURE_USAGES,
+            }),
+        }
+    }
+}
+
+// texture usages that have a `GpuTextureUsage` counterpart.
+const WEBGPU_TEXTURE_USAGES: wgpu_types::TextureUsages = wgpu_types::TextureUsages::COPY_SRC
+    .union(wgpu_types::TextureUsages::COPY_DST)
+    .union(wgpu_types::TextureUsages::TEXTURE_BINDING)
+    .union(wgpu_types::TextureUsages::STORAGE_BINDING)
+    .union(wgpu_types::TextureUsages::RENDER_ATTACHMENT);
+
+impl ContextConfiguration {
+    // configure the surface again with the stored configuration, at a new size.
+    fn reconfigure(
+        &mut self,
+        instance: &wgpu_core::global::Global,
+        surface_id: wgpu_core::id::SurfaceId,
+        width: u32,
+        height: u32,
+    ) -> Result<(), wgpu_core::present::ConfigureSurfaceError> {
+        self.config.width = width;
+        self.config.height = height;
+        let device_id = *self.device.device_id();
+        match instance.surface_configure(surface_id, device_id, &self.config) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    // a texture to render to when the surface has no frame to give, which is never presented.
+    fn skipped_frame(&mut self, instance: &wgpu_core::global::Global) -> webgpu::GpuTexture {
+        let descriptor = wgpu_types::TextureDescriptor {
+            label: None,
+            size: wgpu_types::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu_types::TextureDimension::D2,
+            format: self.config.format,
+            usage: self.config.usage,
+            view_formats: self.config.view_formats.clone(),
+        };
+        let (texture_id, err) =
+            instance.device_create_texture(*self.device.device_id(), &descriptor, None);
+        self.device.error_handler().handle_possible_error(err);
+        self.skip_present = true;
+        // SAFETY: the texture was just created on the configured device.
+        unsafe { self.device.connect_texture(texture_id, &self.config) }
+    }
+}
+
+/// Present modes and frame latencies guests get, chosen by the embedder.
+#[derive(Clone, Debug)]
+pub struct PresentPolicy {
+    /// Used when the guest doesn't ask for a present mode, or asks for one that isn't allowed or supported.
+    pub default_present_mode: wgpu_types::PresentMode,
+    /// Present modes guests may ask for.
+    pub allowed_present_modes: Vec<wgpu_types::PresentMode>,
+    /// Used when the guest doesn't ask for a frame latency.
+    pub default_frame_latency: u32,
+    /// Guests asking for a higher frame latency get this instead.
+    pub max_frame_latency: u32,
+}
+
+impl Default for PresentPolicy {
+    fn default() -> Self {
+        Self {
+            default_present_mode: wgpu_types::PresentMode::default(),
+            allowed_present_modes: vec![
+                wgpu_types::PresentMode::AutoVsync,
+                wgpu_types::PresentMode::AutoNoVsync,
+                wgpu_types::PresentMode::Fifo,
+                wgpu_types::PresentMode::FifoRelaxed,
+                wgpu_types::PresentMode::Immediate,
+                wgpu_types::PresentMode::Mailbox,
+            ],
+            default_frame_latency: 2,
+            max_frame_latency: 3,
+        }
+    }
+}
+
+impl PresentPolicy {
+    // `supported` are the present modes from the surface's capabilities.
+    fn present_mode(
+        &self,
+        requested: Option<wgpu_types::PresentMode>,
+        supported: &[wgpu_types::PresentMode],
+    ) -> wgpu_types::PresentMode {
+        // wgpu resolves the auto modes to a supported mode itself.
+        let is_supported = |mode: wgpu_types::PresentMode| {
+            matches!(
+                mode,
+                wgpu_types::PresentMode::AutoVsync | wgpu_types::PresentMode::AutoNoVsync
+            ) || supported.contains(&mode)
+        };
+        let fallback = if is_supported(self.default_present_mode) {
+            self.default_present_mode
+        } else {
+            // every surface supports fifo.
+            wgpu_types::PresentMode::Fifo
+        };
+        match requested {
+            None => fallback,
+            Some(mode) if self.allowed_present_modes.contains(&mode) && is_supported(mode) => mode,
+            Some(mode) => {
+                log::warn!("present mode {mode:?} isn't allowed or supported, using {fallback:?}");
+                fallback
+            }
+        }
+    }
+
+    fn frame_latency(&self, requested: Option<u32>) -> u32 {
+        requested
+            .unwrap_or(self.default_frame_latency)
+            .clamp(1, self.max_frame_latency.max(1))
+    }
+}
+
+impl From<surface_webgpu_ext::PresentMode> for wgpu_types::PresentMode {
+    fn from(value: surface_webgpu_ext::PresentMode) -> Self {
+        match value {
+            surface_webgpu_ext::PresentMode::AutoVsync => wgpu_types::PresentMode::AutoVsync,
+            surface_webgpu_ext::PresentMode::AutoNoVsync => wgpu_types::PresentMode::AutoNoVsync,
+            surface_webgpu_ext::PresentMode::Fifo => wgpu_types::PresentMode::Fifo,
+            surface_webgpu_ext::PresentMode::FifoRelaxed => wgpu_types::PresentMode::FifoRelaxed,
+            surface_webgpu_ext::PresentMode::Immediate => wgpu_types::PresentMode::Immediate,
+            surface_webgpu_ext::PresentMode::Mailbox => wgpu_types::PresentMode::Mailbox,
+        }
+    }
+}
+
+impl From<wgpu_types::PresentMode> for surface_webgpu_ext::PresentMode {
+    fn from(value: wgpu_types::PresentMode) -> Self {
+        match value {
+            wgpu_types::PresentMode::AutoVsync => surface_webgpu_ext::PresentMode::AutoVsync,
+            wgpu_types::PresentMode::AutoNoVsync => surface_webgpu_ext::PresentMode::AutoNoVsync,
+            wgpu_types::PresentMode::Fifo => surface_webgpu_ext::PresentMode::Fifo,
+            wgpu_types::PresentMode::FifoRelaxed => surface_webgpu_ext::PresentMode::FifoRelaxed,
+            wgpu_types::PresentMode::Immediate => surface_webgpu_ext::PresentMode::Immediate,
+            wgpu_types::PresentMode::Mailbox => surface_webgpu_ext::PresentMode::Mailbox,
+        }
+    }
+}
+
+// `None` for the alpha modes WebGPU has no counterpart for.
+fn canvas_alpha_mode(value: wgpu_types::CompositeAlphaMode) -> Option<webgpu::GpuCanvasAlphaMode> {
+    match value {
+        wgpu_types::CompositeAlphaMode::Opaque => Some(webgpu::GpuCanvasAlphaMode::Opaque),
+        wgpu_types::CompositeAlphaMode::PreMultiplied => {
+            Some(webgpu::GpuCanvasAlphaMode::Premultiplied)
+        }
+        wgpu_types::CompositeAlphaMode::Auto
+        | wgpu_types::CompositeAlphaMode::PostMultiplied
+        | wgpu_types::CompositeAlphaMode::Inherit => None,
+    }
+}
+
+// Color spaces surfaces can be presented in.
+// wgpu-core has no way to pick a surface's color space, so `display-p3` isn't supported.
+const SUPPORTED_COLOR_SPACES: [webgpu::PredefinedColorSpace; 1] =
+    [webgpu::PredefinedColorSpace::Srgb];
+
+// The color space and tone mapping a surface with `format` is presented with, when the guest asks for `color_space` and `tone_mapping`.
+// Unsupported color spaces fall back to sRGB.
+// Backends present `Rgba16Float` surfaces as extended range sRGB (scRGB), so extended tone mapping works with that format.
+fn output_color(
+    format: wgpu_types::TextureFormat,
+    color_space: Option<webgpu::PredefinedColorSpace>,
+    tone_mapping: Option<webgpu::GpuCanvasToneMapping>,
+) -> (
+    webgpu::PredefinedColorSpace,
+    webgpu::GpuCanvasToneMappingMode,
+) {
+    let color_space = color_space
+        .filter(|color_space| SUPPORTED_COLOR_SPACES.contains(color_space))
+        .unwrap_or(webgpu::PredefinedColorSpace::Srgb);
+    // https://www.w3.org/TR/webgpu/#dom-gpucanvastonemappingmode-extended
+    // other formats can't hold values outside of the standard range in the first place.
+    let tone_mapping_mode = match tone_mapping.and_then(|t| t.mode) {
+        Some(webgpu::GpuCanvasToneMappingMode::Extended)
+            if format == wgpu_types::TextureFormat::Rgba16Float =>
+        {
+            webgpu::GpuCanvasToneMappingMode::Extended
+        }
+        _ => webgpu::GpuCanvasToneMappingMode::Standard,
+    };
+    (color_space, tone_mapping_mode)
+}
+
+// Checks the guest's configuration against what the surface supports, since wgpu-core would either panic or give less helpful errors.
+// https://www.w3.org/TR/webgpu/#dom-gpucanvascontext-configure
+fn validate_configuration(
+    capabilities: &wgpu_types::SurfaceCapabilities,
+    configuration: &surface_webgpu::ContextConfiguration,
+    options: &surface_webgpu_ext::PresentOptions,
+    present_policy: &PresentPolicy,
+    width: u32,
+    height: u32,
+) -> Result<wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>, ValidationError> {
+    let format: wgpu_types::TextureFormat = configuration.format.into();
+    if !capabilities.formats.contains(&format) {
+        return Err(ValidationError(format!(
+            "Format {format:?} isn't supported by the surface, supported formats are {:?}",
+            capabilities.formats
+        )));
+    }
+
+    // present in WebGPU, same defaults https://www.w3.org/TR/webgpu/#dictdef-gpucanvasconfiguration
+    let usage: wgpu_types::TextureUsages = configuration
+        .usage
+        .unwrap_or(webgpu::GpuTextureUsage::RENDER_ATTACHMENT)
+        .try_into()
+        .map_err(|e| ValidationError(format!("Invalid usage: {e}")))?;
+    if !capabilities.usages.contains(usage) {
+        return Err(ValidationError(format!(
+            "Usage {usage:?} isn't supported by the surface, supported usages are {:?}",
+            capabilities.usages
+        )));
+    }
+
+    let view_formats: Vec<wgpu_types::TextureFormat> = configuration
+        .view_formats
+        .iter()
+        .flatten()
+        .map(|&f| f.into())
+        .collect();
+    // https://www.w3.org/TR/webgpu/#texture-view-format-compatible
+    // > format and viewFormat may only differ in whether they are srgb formats (have the -srgb suffix).
+    if let Some(view_format) = view_formats
+        .iter()
+        .find(|f| f.remove_srgb_suffix() != format.remove_srgb_suffix())
+    {
+        return Err(ValidationError(format!(
+            "View format {view_format:?} isn't compatible with format {format:?}"
+        )));
+    }
+
+    let alpha_mode = match configuration
+        .alpha_mode
+        .unwrap_or(webgpu::GpuCanvasAlphaMode::Opaque)
+    {
+        // every surface can be presented opaque, but some only list it as `Auto` or `Inherit`.
+        webgpu::GpuCanvasAlphaMode::Opaque
+            if !capabilities
+                .alpha_modes
+                .contains(&wgpu_types::CompositeAlphaMode::Opaque) =>
+        {
+            wgpu_types::CompositeAlphaMode::Auto
+        }
+        alpha_mode => {
+            let alpha_mode = alpha_mode.into();
+            if !capabilities.alpha_modes.contains(&alpha_mode) {
+                return Err(ValidationError(format!(
+                    "Alpha mode {alpha_mode:?} isn't supported by the surface, supported alpha modes are {:?}",
+                    capabilities.alpha_modes
+                )));
+            }
+            alpha_mode
+        }
+    };
+
+    Ok(wgpu_types::SurfaceConfiguration {
+        format,
+        usage,
+        view_formats,
+        alpha_mode,
+        // not present in WebGPU
+        width,
+        height,
+        present_mode: present_policy.present_mode(
+            options.present_mode.map(Into::into),
+            &capabilities.present_modes,
+        ),
+        desired_maximum_frame_latency: present_policy
+            .frame_latency(options.desired_maximum_frame_latency),
+    })
+}
+
+// how many times get-current-texture reconfigures an outdated or lost surface before giving up.
+const MAX_RECONFIGURE_ATTEMPTS: usize = 2;
+
+// What get-current-texture does with what the surface gave back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SurfaceTextureAction {
+    Use,
+    Reconfigure,
+    // lost surfaces can't be configured again, they have to be created again.
+    Recreate,
+    // the surface has no frame to give right now, e.g. it timed out or the window is occluded.
+    // The guest renders to a texture that's never presented, rather than trapping.
+    Skip,
+}
+
+fn surface_texture_action(
+    status: &wgpu_types::SurfaceStatus,
+    has_texture: bool,
+    attempts: usize,
+) -> SurfaceTextureAction {
+    match status {
+        wgpu_types::SurfaceStatus::Good | wgpu_types::SurfaceStatus::Suboptimal if has_texture => {
+            SurfaceTextureAction::Use
+        }
+        wgpu_types::SurfaceStatus::Outdated if attempts < MAX_RECONFIGURE_ATTEMPTS => {
+            SurfaceTextureAction::Reconfigure
+        }
+        wgpu_types::SurfaceStatus::Lost if attempts < MAX_RECONFIGURE_ATTEMPTS => {
+            SurfaceTextureAction::Recreate
+        }
+        _ => SurfaceTextureAction::Skip,
+    }
+}
+
+// Creates the wgpu surface for a window on the main thread.
+fn create_wgpu_surface<S: MainThreadSpawner>(
+    instance: &Arc<wgpu_core::global::Global>,
+    main_thread_spawner: &S,
+    surface: &surface_webgpu::Surface,
+) -> Result<wgpu_core::id::SurfaceId, wgpu_core::instance::CreateSurfaceError> {
+    let instance = Arc::clone(instance);
+    let surface = surface.arc_clone();
+    futures::executor::block_on(main_thread_spawner.spawn(move || {
+        // SAFETY: The raw handles remain valid for the lifetime of the wgpu surface because
+        // `Context` holds an `arc_clone()` of the surface alongside the `surface_id`.
+        unsafe {
+            instance.instance_create_surface(
+                Some(surface.display_handle().unwrap().as_raw()),
+                surface.window_handle().unwrap().as_raw(),
+                None,
+            )
+        }
+    }))
+}
+
+// Releases a wgpu surface where it was created, which has to happen before the window it presents to is dropped.
+fn drop_wgpu_surface<S: MainThreadSpawner>(
+    instance: &Arc<wgpu_core::global::Global>,
+    main_thread_spawner: &S,
+    surface_id: wgpu_core::id::SurfaceId,
+) {
+    let instance = Arc::clone(instance);
+    futures::executor::block_on(
+        main_thread_spawner.spawn(move || instance.surface_drop(surface_id)),
+    );
 }
 
 // linker connection
@@ -42,6 +485,10 @@ where
         l,
         T::surface_webgpu_ctx,
     )?;
+    wasi_gfx::surface_ext::surface_webgpu_ext::add_to_linker::<_, HasSurfaceWebgpu<T::Spawner>>(
+        l,
+        T::surface_webgpu_ctx,
+    )?;
     Ok(())
 }
 
@@ -55,6 +502,7 @@ pub struct SurfaceWebgpuCtx<'a, S: MainThreadSpawner> {
     pub table: &'a mut wasmtime_wasi::ResourceTable,
     pub instance: &'a Arc<wasi_webgpu_wasmtime::reexports::wgpu_core::global::Global>,
     pub main_thread_spawner: &'a S,
+    pub present_policy: &'a PresentPolicy,
 }
 
 struct HasSurfaceWebgpu<S>(PhantomData<S>);
@@ -63,6 +511,113 @@ impl<S: MainThreadSpawner> HasData for HasSurfaceWebgpu<S> {
     type Data<'a> = SurfaceWebgpuCtx<'a, S>;
 }
 
+impl<'a, S: MainThreadSpawner> SurfaceWebgpuCtx<'a, S> {
+    fn configure_context(
+        &mut self,
+        context: Resource<surface_webgpu::Context>,
+        configuration: surface_webgpu::ContextConfiguration,
+        options: surface_webgpu_ext::PresentOptions,
+    ) -> wasmtime::Result<()> {
+        let device = self.table.get(&configuration.device)?.clone();
+
+        let (color_space, tone_mapping_mode) = output_color(
+            configuration.format.into(),
+            configuration.color_space,
+            configuration.tone_mapping,
+        );
+        if configuration.color_space.is_some_and(|c| c != color_space) {
+            log::warn!(
+                "Color space {:?} isn't supported, using {color_space:?}",
+                configuration.color_space
+            );
+        }
+        if configuration
+            .tone_mapping
+            .as_ref()
+            .and_then(|t| t.mode)
+            .is_some_and(|mode| mode != tone_mapping_mode)
+        {
+            log::warn!(
+                "Extended tone mapping needs an Rgba16Float surface, using standard tone mapping"
+            );
+        }
+
+        let context = self.table.get_mut(&context)?;
+        let (surface_id, context_configuration) = match &mut context.target {
+            ContextTarget::Window {
+                surface_id,
+                configuration,
+            } => (*surface_id, configuration),
+            ContextTarget::Offscreen(canvas) => {
+                canvas.resize(
+                    self.instance,
+                    context.surface.width(),
+                    context.surface.height(),
+                );
+                canvas.configure(
+                    self.instance,
+                    device,
+                    webgpu::GpuCanvasConfiguration {
+                        device: Resource::new_borrow(configuration.device.rep()),
+                        format: configuration.format,
+                        usage: configuration.usage,
+                        view_formats: configuration.view_formats,
+                        color_space: configuration.color_space,
+                        tone_mapping: configuration.tone_mapping,
+                        alpha_mode: configuration.alpha_mode,
+                    },
+                );
+                return Ok(());
+            }
+        };
+
+        // the previous configuration is gone even if the new one turns out to be invalid.
+        if let Some(previous) = context_configuration.take() {
+            previous.device.release(self.instance);
+        }
+
+        let (width, height) = (context.surface.width(), context.surface.height());
+        let config = match self
+            .instance
+            .surface_get_capabilities(surface_id, *device.adapter_id())
+            .map_err(|e| ValidationError(format!("Surface can't be used with this device: {e}")))
+            .and_then(|capabilities| {
+                validate_configuration(
+                    &capabilities,
+                    &configuration,
+                    &options,
+                    self.present_policy,
+                    width,
+                    height,
+                )
+            }) {
+            Ok(config) => config,
+            Err(err) => {
+                device.error_handler().handle_possible_error(Some(err));
+                device.release(self.instance);
+                return Ok(());
+            }
+        };
+        let err = self
+            .instance
+            .surface_configure(surface_id, *device.device_id(), &config);
+        if let Some(err) = err {
+            device.error_handler().handle_possible_error(Some(err));
+            device.release(self.instance);
+            return Ok(());
+        }
+
+        *context_configuration = Some(ContextConfiguration {
+            device,
+            config,
+            color_space,
+            tone_mapping_mode,
+            skip_present: false,
+        });
+        Ok(())
+    }
+}
+
 // wasmtime trait impls
 impl<'a, S: MainThreadSpawner> surface_webgpu::Host for SurfaceWebgpuCtx<'a, S> {}
 
@@ -72,27 +627,24 @@ impl<'a, S: MainThreadSpawner> surface_webgpu::HostContext for SurfaceWebgpuCtx<
         surface: Resource<surface_webgpu::Surface>,
     ) -> wasmtime::Result<Resource<surface_webgpu::Context>> {
         let surface = self.table.get(&surface)?;
-        let instance = Arc::clone(self.instance);
-
-        let surface_id = futures::executor::block_on({
-            let surface = surface.arc_clone();
-            self.main_thread_spawner.spawn(move || {
-                // SAFETY: The raw handles remain valid for the lifetime of the wgpu surface because
-                // `Context` holds an `arc_clone()` of the surface alongside the `surface_id`.
-                unsafe {
-                    instance.instance_create_surface(
-                        Some(surface.display_handle().unwrap().as_raw()),
-                        surface.window_handle().unwrap().as_raw(),
-                        None,
-                    )
-                }
-            })
-        })?;
+
+        if surface.frame_sink().is_some() {
+            let canvas =
+                wasi_webgpu_wasmtime::CanvasContext::new(surface.width(), surface.height());
+            return Ok(self.table.push(Context {
+                surface: surface.arc_clone(),
+                target: ContextTarget::Offscreen(canvas),
+            })?);
+        }
+
+        let surface_id = create_wgpu_surface(self.instance, self.main_thread_spawner, surface)?;
 
         Ok(self.table.push(Context {
             surface: surface.arc_clone(),
-            surface_id,
-            configuration: None,
+            target: ContextTarget::Window {
+                surface_id,
+                configuration: None,
+            },
         })?)
     }
 
@@ -101,39 +653,26 @@ impl<'a, S: MainThreadSpawner> surface_webgpu::HostContext for SurfaceWebgpuCtx<
         context: Resource<surface_webgpu::Context>,
         configuration: surface_webgpu::ContextConfiguration,
     ) -> wasmtime::Result<()> {
-        let device_id = *self.table.get(&configuration.device)?.device_id();
-
-        let context = self.table.get_mut(&context)?;
-
-        let err = self.instance.surface_configure(
-            context.surface_id,
-            device_id,
-            &wgpu_types::SurfaceConfiguration {
-                // present in WebGPU, same defaults https://www.w3.org/TR/webgpu/#dictdef-gpucanvasconfiguration
-                format: configuration.format.into(),
-                usage: configuration.usage.unwrap_or(wasi_webgpu_wasmtime::wasi::webgpu::webgpu::GpuTextureUsage::RENDER_ATTACHMENT).try_into().unwrap(),
-                view_formats: configuration.view_formats.into_iter().flatten().map(|f| f.into()).collect(),
-                alpha_mode: configuration.alpha_mode.unwrap_or(wasi_webgpu_wasmtime::wasi::webgpu::webgpu::GpuCanvasAlphaMode::Opaque).into(),
-                // not present in WebGPU
-                width: context.surface.width(),
-                height: context.surface.height(),
-                present_mode: wgpu_types::PresentMode::default(),
-                desired_maximum_frame_latency: 2,
-            }
-        );
-        if let Some(err) = err {
-            bail!("{err:#?}")
-        }
-
-        context.configuration = Some(ContextConfiguration {
-            device: configuration.device,
-        });
-        Ok(())
+        self.configure_context(
+            context,
+            configuration,
+            surface_webgpu_ext::PresentOptions {
+                present_mode: None,
+                desired_maximum_frame_latency: None,
+            },
+        )
     }
 
     fn unconfigure(&mut self, context: Resource<surface_webgpu::Context>) -> wasmtime::Result<()> {
         let context = self.table.get_mut(&context)?;
-        context.configuration = None;
+        match &mut context.target {
+            ContextTarget::Window { configuration, .. } => {
+                if let Some(configuration) = configuration.take() {
+                    configuration.device.release(self.instance);
+                }
+            }
+            ContextTarget::Offscreen(canvas) => canvas.unconfigure(self.instance),
+        }
         Ok(())
     }
 
@@ -141,36 +680,474 @@ impl<'a, S: MainThreadSpawner> surface_webgpu::HostContext for SurfaceWebgpuCtx<
         &mut self,
         context: Resource<surface_webgpu::Context>,
     ) -> wasmtime::Result<Resource<surface_webgpu::GpuTexture>> {
-        let context = self.table.get(&context)?;
+        let context = self.table.get_mut(&context)?;
 
-        let Some(configuration) = &context.configuration else {
-            bail!("Not configured")
+        let (surface_id, configuration) = match &mut context.target {
+            ContextTarget::Window {
+                surface_id,
+                configuration,
+            } => {
+                let Some(configuration) = configuration else {
+                    bail!("Not configured")
+                };
+                (surface_id, configuration)
+            }
+            ContextTarget::Offscreen(canvas) => {
+                // the canvas follows the size of the surface, like a swapchain would.
+                let (width, height) = (context.surface.width(), context.surface.height());
+                if (canvas.width(), canvas.height()) != (width, height) {
+                    canvas.resize(self.instance, width, height);
+                }
+                let texture = canvas.get_current_texture()?;
+                return Ok(self.table.push(texture)?);
+            }
         };
+        configuration.skip_present = false;
 
-        let texture_id: wgpu_core::id::TextureId = self
-            .instance
-            .surface_get_current_texture(context.surface_id, None)
-            .unwrap()
-            .texture
-            .unwrap();
+        // the window was resized since the surface was configured.
+        let (width, height) = (context.surface.width(), context.surface.height());
+        let resized = (configuration.config.width, configuration.config.height) != (width, height);
+        // can't configure a surface with a zero size, e.g. while minimized. Keep the old size until it's visible again.
+        if resized && width != 0 && height != 0 {
+            if let Err(err) = configuration.reconfigure(self.instance, *surface_id, width, height) {
+                configuration
+                    .device
+                    .error_handler()
+                    .handle_possible_error(Some(err));
+                let texture = configuration.skipped_frame(self.instance);
+                return Ok(self.table.push(texture)?);
+            }
+        }
 
-        let device = self.table.get(&configuration.device)?;
+        let mut attempts = 0;
+        let texture_id = loop {
+            let output = match self.instance.surface_get_current_texture(*surface_id, None) {
+                Ok(output) => output,
+                Err(err) => {
+                    configuration
+                        .device
+                        .error_handler()
+                        .handle_possible_error(Some(err));
+                    let texture = configuration.skipped_frame(self.instance);
+                    return Ok(self.table.push(texture)?);
+                }
+            };
+            let action = surface_texture_action(&output.status, output.texture.is_some(), attempts);
+            let reconfigured = match (action, output.texture) {
+                (SurfaceTextureAction::Use, Some(texture_id)) => break texture_id,
+                (SurfaceTextureAction::Reconfigure, _) => {
+                    let (width, height) = (configuration.config.width, configuration.config.height);
+                    configuration.reconfigure(self.instance, *surface_id, width, height)
+                }
+                (SurfaceTextureAction::Recreate, _) => {
+                    let lost = std::mem::replace(
+                        surface_id,
+                        create_wgpu_surface(
+                            self.instance,
+                            self.main_thread_spawner,
+                            &context.surface,
+                        )?,
+                    );
+                    drop_wgpu_surface(self.instance, self.main_thread_spawner, lost);
+                    let (width, height) = (configuration.config.width, configuration.config.height);
+                    configuration.reconfigure(self.instance, *surface_id, width, height)
+                }
+                _ => {
+                    log::debug!(
+                        "Skipping a frame, the surface status is {:?}",
+                        output.status
+                    );
+                    let texture = configuration.skipped_frame(self.instance);
+                    return Ok(self.table.push(texture)?);
+                }
+            };
+            if let Err(err) = reconfigured {
+                configuration
+                    .device
+                    .error_handler()
+                    .handle_possible_error(Some(err));
+                let texture = configuration.skipped_frame(self.instance);
+                return Ok(self.table.push(texture)?);
+            }
+            attempts += 1;
+        };
 
         // SAFETY: surface_get_current_texture will only give back a texture connected to the configured device.
-        let texture = unsafe { device.connect_texture(texture_id) };
+        let texture = unsafe {
+            configuration
+                .device
+                .connect_texture(texture_id, &configuration.config)
+        };
 
         Ok(self.table.push(texture)?)
     }
 
     fn present(&mut self, context: Resource<surface_webgpu::Context>) -> wasmtime::Result<()> {
-        let surface_id = self.table.get(&context)?.surface_id;
-
-        self.instance.surface_present(surface_id)?;
+        let context = self.table.get_mut(&context)?;
+        match &mut context.target {
+            ContextTarget::Window {
+                surface_id,
+                configuration,
+            } => {
+                // nothing was acquired from the surface for a skipped frame.
+                if let Some(configuration) = configuration.as_mut().filter(|c| c.skip_present) {
+                    configuration.skip_present = false;
+                    return Ok(());
+                }
+                context.surface.pre_present_notify();
+                self.instance.surface_present(*surface_id)?;
+            }
+            ContextTarget::Offscreen(canvas) => {
+                canvas.present();
+                // read back only once someone asks for the frame.
+                if let Some(frame) = canvas.presented_frame(self.instance) {
+                    let frame_sink = context.surface.frame_sink().unwrap();
+                    frame_sink.present(PresentedFrame::Webgpu(frame));
+                }
+            }
+        }
         Ok(())
     }
 
     fn drop(&mut self, surface: Resource<surface_webgpu::Context>) -> wasmtime::Result<()> {
-        self.table.delete(surface)?;
+        let context = self.table.delete(surface)?;
+        match context.target {
+            ContextTarget::Window {
+                surface_id,
+                configuration,
+            } => {
+                if let Some(configuration) = configuration {
+                    configuration.device.release(self.instance);
+                }
+                // released before `context.surface`, whose window it presents to.
+                drop_wgpu_surface(self.instance, self.main_thread_spawner, surface_id);
+            }
+            ContextTarget::Offscreen(mut canvas) => canvas.unconfigure(self.instance),
+        }
         Ok(())
     }
 }
+
+impl<'a, S: MainThreadSpawner> surface_webgpu_ext::Host for SurfaceWebgpuCtx<'a, S> {
+    fn configure(
+        &mut self,
+        context: Resource<surface_webgpu::Context>,
+        configuration: surface_webgpu::ContextConfiguration,
+        options: surface_webgpu_ext::PresentOptions,
+    ) -> wasmtime::Result<()> {
+        self.configure_context(context, configuration, options)
+    }
+
+    fn get_configuration(
+        &mut self,
+        context: Resource<surface_webgpu::Context>,
+    ) -> wasmtime::Result<Option<surface_webgpu_ext::ContextConfigurationOwned>> {
+        let context = self.table.get(&context)?;
+        let device = match &context.target {
+            ContextTarget::Window { configuration, .. } => {
+                configuration.as_ref().map(|c| c.device.clone())
+            }
+            ContextTarget::Offscreen(canvas) => canvas.configuration().map(|c| c.device.clone()),
+        };
+        let (Some(config), Some((color_space, tone_mapping_mode)), Some(device)) =
+            (context.configuration(), context.output_color(), device)
+        else {
+            return Ok(None);
+        };
+        let device = self.table.push(device)?;
+        // defaults filled in, like `GpuCanvasContext.getConfiguration` https://www.w3.org/TR/webgpu/#dom-gpucanvascontext-getconfiguration
+        // and the values actually in use, where they differ from the guest's.
+        Ok(Some(surface_webgpu_ext::ContextConfigurationOwned {
+            device,
+            format: config.format.into(),
+            usage: Some(config.usage.try_into()?),
+            view_formats: Some(config.view_formats.into_iter().map(Into::into).collect()),
+            color_space: Some(color_space),
+            tone_mapping: Some(webgpu::GpuCanvasToneMapping {
+                mode: Some(tone_mapping_mode),
+            }),
+            // surfaces that only list opaque as `Auto` get configured with `Auto`, see `validate_configuration`.
+            alpha_mode: Some(
+                canvas_alpha_mode(config.alpha_mode).unwrap_or(webgpu::GpuCanvasAlphaMode::Opaque),
+            ),
+            present_mode: Some(config.present_mode.into()),
+            desired_maximum_frame_latency: Some(config.desired_maximum_frame_latency),
+        }))
+    }
+
+    fn get_capabilities(
+        &mut self,
+        context: Resource<surface_webgpu::Context>,
+        device: Resource<webgpu::GpuDevice>,
+    ) -> wasmtime::Result<surface_webgpu_ext::ContextCapabilities> {
+        let context = self.table.get(&context)?;
+        let device = self.table.get(&device)?;
+        // an empty list of formats tells the guest the surface can't be used with this device.
+        let capabilities = context
+            .capabilities(self.instance, device)
+            .unwrap_or_default();
+
+        let mut alpha_modes = vec![webgpu::GpuCanvasAlphaMode::Opaque];
+        if capabilities
+            .alpha_modes
+            .contains(&wgpu_types::CompositeAlphaMode::PreMultiplied)
+        {
+            alpha_modes.push(webgpu::GpuCanvasAlphaMode::Premultiplied);
+        }
+        // wgpu resolves the auto modes to a supported mode itself.
+        let present_modes = [
+            wgpu_types::PresentMode::AutoVsync,
+            wgpu_types::PresentMode::AutoNoVsync,
+        ]
+        .into_iter()
+        .chain(capabilities.present_modes)
+        .filter(|mode| self.present_policy.allowed_present_modes.contains(mode))
+        .map(Into::into)
+        .collect();
+
+        Ok(surface_webgpu_ext::ContextCapabilities {
+            formats: capabilities.formats.into_iter().map(Into::into).collect(),
+            usages: (capabilities.usages & WEBGPU_TEXTURE_USAGES).try_into()?,
+            alpha_modes,
+            present_modes,
+            color_spaces: SUPPORTED_COLOR_SPACES.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu_types::PresentMode;
+
+    fn capabilities() -> wgpu_types::SurfaceCapabilities {
+        wgpu_types::SurfaceCapabilities {
+            formats: vec![wgpu_types::TextureFormat::Bgra8Unorm],
+            present_modes: vec![PresentMode::Fifo],
+            alpha_modes: vec![wgpu_types::CompositeAlphaMode::Opaque],
+            usages: wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+        }
+    }
+
+    fn configuration(format: webgpu::GpuTextureFormat) -> surface_webgpu::ContextConfiguration {
+        surface_webgpu::ContextConfiguration {
+            device: Resource::new_borrow(0),
+            format,
+            usage: None,
+            view_formats: None,
+            color_space: None,
+            tone_mapping: None,
+            alpha_mode: None,
+        }
+    }
+
+    fn validate(
+        capabilities: &wgpu_types::SurfaceCapabilities,
+        configuration: &surface_webgpu::ContextConfiguration,
+    ) -> Result<wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>, ValidationError>
+    {
+        let options = surface_webgpu_ext::PresentOptions {
+            present_mode: None,
+            desired_maximum_frame_latency: None,
+        };
+        validate_configuration(
+            capabilities,
+            configuration,
+            &options,
+            &PresentPolicy::default(),
+            640,
+            480,
+        )
+    }
+
+    #[test]
+    fn validate_supported_configuration() {
+        let mut configuration = configuration(webgpu::GpuTextureFormat::Bgra8unorm);
+        configuration.view_formats = Some(vec![webgpu::GpuTextureFormat::Bgra8unormSrgb]);
+        let config = validate(&capabilities(), &configuration).unwrap();
+        assert_eq!(config.format, wgpu_types::TextureFormat::Bgra8Unorm);
+        assert_eq!(config.usage, wgpu_types::TextureUsages::RENDER_ATTACHMENT);
+        assert_eq!(
+            config.view_formats,
+            [wgpu_types::TextureFormat::Bgra8UnormSrgb]
+        );
+        assert_eq!(config.alpha_mode, wgpu_types::CompositeAlphaMode::Opaque);
+        assert_eq!((config.width, config.height), (640, 480));
+
+        // surfaces that only list opaque as `Auto` are configured with `Auto`.
+        let capabilities = wgpu_types::SurfaceCapabilities {
+            alpha_modes: vec![wgpu_types::CompositeAlphaMode::Auto],
+            ..capabilities()
+        };
+        let config = validate(&capabilities, &configuration).unwrap();
+        assert_eq!(config.alpha_mode, wgpu_types::CompositeAlphaMode::Auto);
+    }
+
+    #[test]
+    fn validate_unsupported_configuration() {
+        let capabilities = capabilities();
+
+        let unsupported_format = configuration(webgpu::GpuTextureFormat::Rgba16float);
+        assert!(validate(&capabilities, &unsupported_format).is_err());
+
+        let mut unsupported_usage = configuration(webgpu::GpuTextureFormat::Bgra8unorm);
+        unsupported_usage.usage = Some(webgpu::GpuTextureUsage::STORAGE_BINDING);
+        assert!(validate(&capabilities, &unsupported_usage).is_err());
+
+        let mut incompatible_view_format = configuration(webgpu::GpuTextureFormat::Bgra8unorm);
+        incompatible_view_format.view_formats = Some(vec![webgpu::GpuTextureFormat::Rgba8unorm]);
+        assert!(validate(&capabilities, &incompatible_view_format).is_err());
+
+        let mut unsupported_alpha_mode = configuration(webgpu::GpuTextureFormat::Bgra8unorm);
+        unsupported_alpha_mode.alpha_mode = Some(webgpu::GpuCanvasAlphaMode::Premultiplied);
+        assert!(validate(&capabilities, &unsupported_alpha_mode).is_err());
+    }
+
+    #[test]
+    fn output_color_fallback() {
+        use webgpu::{GpuCanvasToneMapping, GpuCanvasToneMappingMode, PredefinedColorSpace};
+
+        let extended = Some(GpuCanvasToneMapping {
+            mode: Some(GpuCanvasToneMappingMode::Extended),
+        });
+        assert_eq!(
+            output_color(wgpu_types::TextureFormat::Bgra8Unorm, None, None),
+            (
+                PredefinedColorSpace::Srgb,
+                GpuCanvasToneMappingMode::Standard
+            )
+        );
+        // display-p3 isn't supported, and isn't listed in the capabilities.
+        assert!(!SUPPORTED_COLOR_SPACES.contains(&PredefinedColorSpace::DisplayP3));
+        assert_eq!(
+            output_color(
+                wgpu_types::TextureFormat::Rgba16Float,
+                Some(PredefinedColorSpace::DisplayP3),
+                extended,
+            ),
+            (
+                PredefinedColorSpace::Srgb,
+                GpuCanvasToneMappingMode::Extended
+            )
+        );
+        // extended tone mapping needs a format that can hold extended range values.
+        assert_eq!(
+            output_color(wgpu_types::TextureFormat::Bgra8Unorm, None, extended),
+            (
+                PredefinedColorSpace::Srgb,
+                GpuCanvasToneMappingMode::Standard
+            )
+        );
+    }
+
+    #[test]
+    fn present_mode_fallback() {
+        let policy = PresentPolicy {
+            default_present_mode: PresentMode::Fifo,
+            allowed_present_modes: vec![PresentMode::AutoNoVsync, PresentMode::Mailbox],
+            ..PresentPolicy::default()
+        };
+        let supported = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ];
+
+        assert_eq!(policy.present_mode(None, &supported), PresentMode::Fifo);
+        assert_eq!(
+            policy.present_mode(Some(PresentMode::Mailbox), &supported),
+            PresentMode::Mailbox
+        );
+        // wgpu resolves the auto modes itself, so they're always supported.
+        assert_eq!(
+            policy.present_mode(Some(PresentMode::AutoNoVsync), &[]),
+            PresentMode::AutoNoVsync
+        );
+        // supported, but not allowed.
+        assert_eq!(
+            policy.present_mode(Some(PresentMode::Immediate), &supported),
+            PresentMode::Fifo
+        );
+        // allowed, but not supported.
+        assert_eq!(
+            policy.present_mode(Some(PresentMode::Mailbox), &[PresentMode::Fifo]),
+            PresentMode::Fifo
+        );
+
+        // an unsupported default falls back to fifo.
+        let policy = PresentPolicy {
+            default_present_mode: PresentMode::Mailbox,
+            ..policy
+        };
+        assert_eq!(
+            policy.present_mode(None, &[PresentMode::Fifo]),
+            PresentMode::Fifo
+        );
+        assert_eq!(policy.present_mode(None, &supported), PresentMode::Mailbox);
+    }
+
+    #[test]
+    fn frame_latency_bounds() {
+        let policy = PresentPolicy {
+            default_frame_latency: 2,
+            max_frame_latency: 3,
+            ..PresentPolicy::default()
+        };
+        assert_eq!(policy.frame_latency(None), 2);
+        assert_eq!(policy.frame_latency(Some(1)), 1);
+        assert_eq!(policy.frame_latency(Some(10)), 3);
+        assert_eq!(policy.frame_latency(Some(0)), 1);
+
+        let policy = PresentPolicy {
+            max_frame_latency: 0,
+            ..policy
+        };
+        assert_eq!(policy.frame_latency(None), 1);
+    }
+
+    #[test]
+    fn surface_texture_status() {
+        use wgpu_types::SurfaceStatus;
+
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Good, true, 0),
+            SurfaceTextureAction::Use
+        );
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Suboptimal, true, 0),
+            SurfaceTextureAction::Use
+        );
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Outdated, false, 0),
+            SurfaceTextureAction::Reconfigure
+        );
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Lost, false, 1),
+            SurfaceTextureAction::Recreate
+        );
+        // frames the surface can't give right now are skipped instead of trapping.
+        for status in [
+            SurfaceStatus::Timeout,
+            SurfaceStatus::Occluded,
+            SurfaceStatus::Validation,
+        ] {
+            assert_eq!(
+                surface_texture_action(&status, false, 0),
+                SurfaceTextureAction::Skip
+            );
+        }
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Good, false, 0),
+            SurfaceTextureAction::Skip
+        );
+        // as are frames the surface still can't give after being configured or created again.
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Outdated, false, MAX_RECONFIGURE_ATTEMPTS),
+            SurfaceTextureAction::Skip
+        );
+        assert_eq!(
+            surface_texture_action(&SurfaceStatus::Lost, false, MAX_RECONFIGURE_ATTEMPTS),
+            SurfaceTextureAction::Skip
+        );
+    }
+}
@@ -4,25 +4,29 @@ use std::{
     fmt::Debug,
     future::Future,
     pin::Pin,
-    sync::{Arc, Mutex},
-    thread::{self, sleep},
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crate::surface::{
-    wasi_gfx, KeyEvent, MainThreadSpawner, PointerEvent, ResizeEvent, SurfaceDesc,
+    pointer_button_mask, touch_pointer_id, wasi_gfx, CursorGrab, CursorIcon, FocusEvent,
+    GesturePhase, KeyEvent, MainThreadSpawner, PinchEvent, PointerButton, PointerEvent,
+    PointerType, ResizeEvent, RotateEvent, SurfaceDesc, VisibilityEvent, WheelDeltaMode,
+    WheelEvent, WindowSize,
 };
-use crate::surface::{GfxWindow, Surface};
+use crate::surface::{GfxWindow, Surface, WeakSurface};
+#[cfg(feature = "input-script")]
+use crate::{InputEvent, InputRecorder};
 use raw_window_handle::{
     DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
 };
 use winit::{
     application::ApplicationHandler,
     dpi::{PhysicalSize, Size},
-    event::{ElementState, WindowEvent},
-    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
+    event::{ElementState, Force, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
     keyboard::ModifiersState,
-    window::{Window, WindowAttributes, WindowId},
+    window::{CursorGrabMode, Fullscreen, Window, WindowAttributes, WindowId},
 };
 
 pub fn create_wasi_winit_event_loop() -> (WasiWinitEventLoop, WasiWinitEventLoopProxy) {
@@ -30,6 +34,9 @@ pub fn create_wasi_winit_event_loop() -> (WasiWinitEventLoop, WasiWinitEventLoop
         event_loop: winit::event_loop::EventLoop::<MainThreadAction>::with_user_event()
             .build()
             .unwrap(),
+        frame_rate: FrameRate::default(),
+        #[cfg(feature = "input-script")]
+        recorder: None,
     };
     let message_sender = WasiWinitEventLoopProxy {
         proxy: event_loop.event_loop.create_proxy(),
@@ -37,27 +44,90 @@ pub fn create_wasi_winit_event_loop() -> (WasiWinitEventLoop, WasiWinitEventLoop
     (event_loop, message_sender)
 }
 
+/// How often surfaces get animation frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrameRate {
+    /// Once per refresh of the monitor the window is on.
+    #[default]
+    Vsync,
+    /// A fixed number of frames per second.
+    Target(f64),
+    /// As often as the event loop gets around to it.
+    Unthrottled,
+}
+
+impl FrameRate {
+    // `None` when unthrottled.
+    fn interval(&self, window: &Window) -> Option<Duration> {
+        match self {
+            FrameRate::Vsync => {
+                let millihertz = window
+                    .current_monitor()
+                    .and_then(|monitor| monitor.refresh_rate_millihertz())
+                    .unwrap_or(DEFAULT_REFRESH_RATE_MILLIHERTZ);
+                Some(Duration::from_secs_f64(1000.0 / millihertz as f64))
+            }
+            FrameRate::Target(fps) => Some(Duration::from_secs_f64(1.0 / fps)),
+            FrameRate::Unthrottled => None,
+        }
+    }
+}
+
+// `PointerEvent::pointer_id` of the mouse, touches start at 1, see `touch_pointer_id`.
+const MOUSE_POINTER_ID: u64 = 0;
+
+// used when the monitor's refresh rate is unknown.
+const DEFAULT_REFRESH_RATE_MILLIHERTZ: u32 = 60_000;
+
+// winit doesn't report a pen's azimuth, so it's taken to lean towards the positive x axis, the default `azimuthAngle`.
+const PEN_AZIMUTH_ANGLE: f64 = 0.0;
+
+// `tilt_x` and `tilt_y` in degrees, from a pen's altitude and azimuth in radians.
+// https://w3c.github.io/pointerevents/#converting-between-tiltx-tilty-and-altitudeangle-azimuthangle
+fn pen_tilt(altitude_angle: f64, azimuth_angle: f64) -> (f32, f32) {
+    let tan_altitude = altitude_angle.tan();
+    // atan2 instead of atan of the quotient, so a pen lying flat doesn't divide by zero.
+    let tilt_x = azimuth_angle.cos().atan2(tan_altitude);
+    let tilt_y = azimuth_angle.sin().atan2(tan_altitude);
+    (tilt_x.to_degrees() as f32, tilt_y.to_degrees() as f32)
+}
+
 pub struct WasiWinitEventLoop {
     event_loop: EventLoop<MainThreadAction>,
+    frame_rate: FrameRate,
+    #[cfg(feature = "input-script")]
+    recorder: Option<Arc<InputRecorder>>,
 }
 
 impl WasiWinitEventLoop {
-    /// This has to be run on the main thread.
-    /// This call will block the thread.
-    pub fn run(self) {
-        let surfaces: Arc<Mutex<HashMap<WindowId, Surface>>> = Default::default();
-
-        {
-            let surfaces = Arc::clone(&surfaces);
-            thread::spawn(move || loop {
-                for proxy in surfaces.lock().unwrap().values() {
-                    proxy.animation_frame();
-                }
-                sleep(Duration::from_millis(16));
-            });
+    /// Defaults to [`FrameRate::Vsync`].
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        if let FrameRate::Target(fps) = frame_rate {
+            assert!(fps > 0.0, "target frame rate must be positive");
         }
+        self.frame_rate = frame_rate;
+    }
+
+    /// Record every input event delivered to the surfaces. Surfaces are numbered in order of creation.
+    #[cfg(feature = "input-script")]
+    pub fn record_input(&mut self, recorder: InputRecorder) {
+        self.recorder = Some(Arc::new(recorder));
+    }
 
-        struct MyWindow(pub Window);
+    /// This has to be run on the main thread.
+    /// This call will block the thread until every window was closed by the user, or [`WasiWinitEventLoopProxy::exit`] is called.
+    /// The guest may still be running when this returns, it's up to the caller to stop it.
+    pub fn run(self) -> EventLoopExit {
+        // Holds a proxy to tell the event loop when the surface is dropped.
+        struct MyWindow(pub Arc<Window>, EventLoopProxy<MainThreadAction>);
+        impl Drop for MyWindow {
+            fn drop(&mut self) {
+                // fails if the event loop already exited, which took the window with it.
+                let _ = self
+                    .1
+                    .send_event(MainThreadAction::DestroyWindow(self.0.id()));
+            }
+        }
         impl HasDisplayHandle for MyWindow {
             fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
                 self.0.display_handle()
@@ -83,14 +153,202 @@ impl WasiWinitEventLoop {
                     height.unwrap_or(self.height()),
                 ));
             }
+
+            fn set_title(&self, title: &str) {
+                self.0.set_title(title);
+            }
+
+            fn fullscreen(&self) -> bool {
+                self.0.fullscreen().is_some()
+            }
+
+            fn set_fullscreen(&self, fullscreen: bool) {
+                self.0
+                    .set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+            }
+
+            fn set_visible(&self, visible: bool) {
+                self.0.set_visible(visible);
+            }
+
+            fn set_resizable(&self, resizable: bool) {
+                self.0.set_resizable(resizable);
+            }
+
+            fn set_decorations(&self, decorations: bool) {
+                self.0.set_decorations(decorations);
+            }
+
+            fn set_min_size(&self, size: Option<WindowSize>) {
+                self.0
+                    .set_min_inner_size(size.map(|s| PhysicalSize::new(s.width, s.height)));
+            }
+
+            fn set_max_size(&self, size: Option<WindowSize>) {
+                self.0
+                    .set_max_inner_size(size.map(|s| PhysicalSize::new(s.width, s.height)));
+            }
+
+            fn set_cursor_visible(&self, visible: bool) {
+                self.0.set_cursor_visible(visible);
+            }
+
+            fn set_cursor_grab(&self, grab: CursorGrab) -> CursorGrab {
+                // platforms support either confined or locked, so fall back to the other one.
+                // See https://docs.rs/winit/0.30/winit/window/enum.CursorGrabMode.html
+                let fallback = match grab {
+                    CursorGrab::None => CursorGrab::None,
+                    CursorGrab::Confined => CursorGrab::Locked,
+                    CursorGrab::Locked => CursorGrab::Confined,
+                };
+                for grab in [grab, fallback] {
+                    match self.0.set_cursor_grab(grab.into()) {
+                        Ok(()) => return grab,
+                        Err(e) => log::debug!("cursor grab {grab:?} failed: {e}"),
+                    }
+                }
+                let _ = self.0.set_cursor_grab(CursorGrabMode::None);
+                CursorGrab::None
+            }
+
+            fn set_cursor_icon(&self, icon: CursorIcon) {
+                self.0.set_cursor(winit::window::CursorIcon::from(icon));
+            }
+
+            fn pre_present_notify(&self) {
+                self.0.pre_present_notify();
+            }
+        }
+
+        // Frames are sent on `RedrawRequested`, which is requested once per frame interval.
+        struct FrameState {
+            next_frame: Instant,
+            occluded: bool,
+            minimized: bool,
+            // hidden after a close request nobody handled.
+            closed: bool,
+        }
+        impl FrameState {
+            fn paused(&self) -> bool {
+                self.occluded || self.minimized || self.closed
+            }
         }
 
         #[derive(Default)]
         struct App {
             pointer_pos: HashMap<WindowId, (f64, f64)>,
             modifiers: HashMap<WindowId, ModifiersState>,
-            surfaces: HashMap<WindowId, Surface>,
-            arc_surfaces: Arc<Mutex<HashMap<WindowId, Surface>>>,
+            // mouse buttons held down, as a `PointerEvent::buttons` bitmask.
+            pointer_buttons: HashMap<WindowId, u16>,
+            // weak, so that the window is destroyed once the last `Surface` is dropped.
+            surfaces: HashMap<WindowId, WeakSurface>,
+            windows: HashMap<WindowId, Arc<Window>>,
+            frames: HashMap<WindowId, FrameState>,
+            frame_rate: FrameRate,
+            // set before exiting the event loop.
+            exit: Option<EventLoopExit>,
+            // always set, `Option` for `Default`.
+            proxy: Option<EventLoopProxy<MainThreadAction>>,
+            #[cfg(feature = "input-script")]
+            recorder: Option<Arc<InputRecorder>>,
+            // index of each surface in recordings, in order of creation.
+            #[cfg(feature = "input-script")]
+            surface_indices: HashMap<WindowId, usize>,
+            #[cfg(feature = "input-script")]
+            next_surface_index: usize,
+        }
+
+        impl App {
+            fn surface(&self, window_id: WindowId) -> Option<Surface> {
+                self.surfaces.get(&window_id)?.upgrade()
+            }
+
+            /// Update whether frames are paused, telling the guest when the window is hidden or shown.
+            fn update_frames(&mut self, window_id: WindowId, update: impl FnOnce(&mut FrameState)) {
+                let Some(frames) = self.frames.get_mut(&window_id) else {
+                    return;
+                };
+                let was_paused = frames.paused();
+                update(frames);
+                frames.next_frame = Instant::now();
+                let paused = frames.paused();
+                if paused == was_paused {
+                    return;
+                }
+                let event = VisibilityEvent { visible: !paused };
+                if let Some(proxy) = self.surface(window_id) {
+                    proxy.visibility_change(event);
+                }
+                #[cfg(feature = "input-script")]
+                self.record(window_id, InputEvent::VisibilityChange(event));
+            }
+
+            fn wheel(
+                &self,
+                window_id: WindowId,
+                delta_x: f64,
+                delta_y: f64,
+                delta_mode: WheelDeltaMode,
+            ) {
+                let (x, y) = *self.pointer_pos.get(&window_id).unwrap();
+                let modifiers = self.modifiers.get(&window_id).copied().unwrap_or_default();
+                let event = WheelEvent {
+                    x,
+                    y,
+                    delta_x,
+                    delta_y,
+                    delta_z: 0.0,
+                    delta_mode,
+                    alt_key: modifiers.alt_key(),
+                    ctrl_key: modifiers.control_key(),
+                    meta_key: modifiers.super_key(),
+                    shift_key: modifiers.shift_key(),
+                };
+                if let Some(proxy) = self.surface(window_id) {
+                    proxy.wheel(event);
+                }
+                #[cfg(feature = "input-script")]
+                self.record(window_id, InputEvent::Wheel(event));
+            }
+
+            /// A mouse event at `(x, y)`, touches override the mouse specific fields.
+            fn pointer_event(
+                &self,
+                window_id: WindowId,
+                x: f64,
+                y: f64,
+                button: Option<PointerButton>,
+            ) -> PointerEvent {
+                let modifiers = self.modifiers.get(&window_id).copied().unwrap_or_default();
+                let buttons = self.pointer_buttons.get(&window_id).copied().unwrap_or(0);
+                PointerEvent {
+                    x,
+                    y,
+                    pointer_id: MOUSE_POINTER_ID,
+                    pointer_type: PointerType::Mouse,
+                    button,
+                    buttons,
+                    // https://www.w3.org/TR/pointerevents/#dom-pointerevent-pressure
+                    pressure: if buttons == 0 { 0.0 } else { 0.5 },
+                    tilt_x: 0.0,
+                    tilt_y: 0.0,
+                    alt_key: modifiers.alt_key(),
+                    ctrl_key: modifiers.control_key(),
+                    meta_key: modifiers.super_key(),
+                    shift_key: modifiers.shift_key(),
+                }
+            }
+
+            #[cfg(feature = "input-script")]
+            fn record(&self, window_id: WindowId, event: InputEvent) {
+                if let (Some(recorder), Some(index), Some(surface)) = (
+                    &self.recorder,
+                    self.surface_indices.get(&window_id),
+                    self.surface(window_id),
+                ) {
+                    recorder.record(*index, &surface, event);
+                }
+            }
         }
 
         impl ApplicationHandler<MainThreadAction> for App {
@@ -98,6 +356,38 @@ impl WasiWinitEventLoop {
                 // TODO:
             }
 
+            fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+                let now = Instant::now();
+                let mut control_flow = ControlFlow::Wait;
+                for (window_id, frames) in &mut self.frames {
+                    if frames.paused() {
+                        continue;
+                    }
+                    let window = &self.windows[window_id];
+                    let Some(interval) = self.frame_rate.interval(window) else {
+                        window.request_redraw();
+                        control_flow = ControlFlow::Poll;
+                        continue;
+                    };
+                    if frames.next_frame <= now {
+                        window.request_redraw();
+                        frames.next_frame += interval;
+                        // don't try to catch up on frames that were missed.
+                        if frames.next_frame <= now {
+                            frames.next_frame = now + interval;
+                        }
+                    }
+                    control_flow = match control_flow {
+                        ControlFlow::Wait => ControlFlow::WaitUntil(frames.next_frame),
+                        ControlFlow::WaitUntil(next) => {
+                            ControlFlow::WaitUntil(next.min(frames.next_frame))
+                        }
+                        ControlFlow::Poll => ControlFlow::Poll,
+                    };
+                }
+                event_loop.set_control_flow(control_flow);
+            }
+
             fn user_event(&mut self, event_loop: &ActiveEventLoop, event: MainThreadAction) {
                 match event {
                     MainThreadAction::CreateWindow(desc, response_channel) => {
@@ -107,47 +397,94 @@ impl WasiWinitEventLoop {
                                 (width as f64, height as f64).into(),
                             ));
                         }
-                        let window = event_loop.create_window(window_options).unwrap();
-                        // TODO: remove when window is drooped.
+                        if let Some(title) = desc.title {
+                            window_options = window_options.with_title(title);
+                        }
+                        if let Some(resizable) = desc.resizable {
+                            window_options = window_options.with_resizable(resizable);
+                        }
+                        if let Some(decorations) = desc.decorations {
+                            window_options = window_options.with_decorations(decorations);
+                        }
+                        if desc.fullscreen == Some(true) {
+                            window_options =
+                                window_options.with_fullscreen(Some(Fullscreen::Borderless(None)));
+                        }
+                        if let Some(visible) = desc.visible {
+                            window_options = window_options.with_visible(visible);
+                        }
+                        let window = Arc::new(event_loop.create_window(window_options).unwrap());
                         self.pointer_pos.insert(window.id(), (0.0, 0.0));
                         self.modifiers
                             .insert(window.id(), ModifiersState::default());
 
                         let window_id = window.id();
+                        #[cfg(feature = "input-script")]
+                        {
+                            self.surface_indices
+                                .insert(window_id, self.next_surface_index);
+                            self.next_surface_index += 1;
+                        }
 
-                        let surface = Surface::new(Box::new(MyWindow(window)));
+                        self.windows.insert(window_id, Arc::clone(&window));
+                        self.frames.insert(
+                            window_id,
+                            FrameState {
+                                next_frame: Instant::now(),
+                                occluded: false,
+                                minimized: false,
+                                closed: false,
+                            },
+                        );
+                        let proxy = self.proxy.clone().unwrap();
+                        let surface = Surface::new(Box::new(MyWindow(window, proxy)));
 
-                        self.surfaces.insert(window_id, surface.arc_clone());
-                        self.arc_surfaces
-                            .lock()
-                            .unwrap()
-                            .insert(window_id, surface.arc_clone());
+                        self.surfaces.insert(window_id, surface.downgrade());
 
                         response_channel.send(surface).unwrap();
                     }
+                    MainThreadAction::DestroyWindow(window_id) => {
+                        // dropping the last `Arc` closes the window.
+                        self.windows.remove(&window_id);
+                        self.surfaces.remove(&window_id);
+                        self.frames.remove(&window_id);
+                        self.pointer_pos.remove(&window_id);
+                        self.modifiers.remove(&window_id);
+                        self.pointer_buttons.remove(&window_id);
+                        #[cfg(feature = "input-script")]
+                        self.surface_indices.remove(&window_id);
+                    }
                     MainThreadAction::Spawn(f, res) => {
                         res.send(f()).unwrap();
                     }
+                    MainThreadAction::Exit => {
+                        self.exit = Some(EventLoopExit::Requested);
+                        event_loop.exit();
+                    }
                 }
             }
 
             fn window_event(
                 &mut self,
-                _event_loop: &ActiveEventLoop,
+                event_loop: &ActiveEventLoop,
                 window_id: WindowId,
                 event: WindowEvent,
             ) {
+                // events can still be queued for windows that were just destroyed.
+                if !self.windows.contains_key(&window_id) {
+                    return;
+                }
                 match event {
                     WindowEvent::CursorMoved { position, .. } => {
                         self.pointer_pos
                             .insert(window_id, (position.x, position.y))
                             .unwrap();
-                        if let Some(proxy) = self.surfaces.get(&window_id) {
-                            proxy.pointer_move(PointerEvent {
-                                x: position.x,
-                                y: position.y,
-                            });
+                        let event = self.pointer_event(window_id, position.x, position.y, None);
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.pointer_move(event);
                         }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::PointerMove(event));
                     }
                     WindowEvent::ModifiersChanged(modifiers) => {
                         self.modifiers.insert(window_id, modifiers.state());
@@ -170,7 +507,15 @@ impl WasiWinitEventLoop {
                             meta_key: modifiers.super_key(),
                             shift_key: modifiers.shift_key(),
                         };
-                        if let Some(proxy) = self.surfaces.get(&window_id) {
+                        #[cfg(feature = "input-script")]
+                        self.record(
+                            window_id,
+                            match input.state {
+                                ElementState::Pressed => InputEvent::KeyDown(event.clone()),
+                                ElementState::Released => InputEvent::KeyUp(event.clone()),
+                            },
+                        );
+                        if let Some(proxy) = self.surface(window_id) {
                             match input.state {
                                 ElementState::Pressed => {
                                     proxy.key_down(event);
@@ -181,13 +526,20 @@ impl WasiWinitEventLoop {
                             }
                         }
                     }
-                    WindowEvent::MouseInput { state, .. } => {
-                        let (pointer_x, pointer_y) = self.pointer_pos.get(&window_id).unwrap();
-                        let event = PointerEvent {
-                            x: *pointer_x,
-                            y: *pointer_y,
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        // buttons without a DOM equivalent aren't reported.
+                        let Ok(button) = PointerButton::try_from(button) else {
+                            return;
                         };
-                        if let Some(proxy) = self.surfaces.get(&window_id) {
+                        let buttons = self.pointer_buttons.entry(window_id).or_default();
+                        match state {
+                            ElementState::Pressed => *buttons |= pointer_button_mask(button),
+                            ElementState::Released => *buttons &= !pointer_button_mask(button),
+                        }
+                        let (pointer_x, pointer_y) = *self.pointer_pos.get(&window_id).unwrap();
+                        let event =
+                            self.pointer_event(window_id, pointer_x, pointer_y, Some(button));
+                        if let Some(proxy) = self.surface(window_id) {
                             match state {
                                 ElementState::Pressed => {
                                     proxy.pointer_down(event);
@@ -197,29 +549,218 @@ impl WasiWinitEventLoop {
                                 }
                             }
                         }
+                        #[cfg(feature = "input-script")]
+                        self.record(
+                            window_id,
+                            match state {
+                                ElementState::Pressed => InputEvent::PointerDown(event),
+                                ElementState::Released => InputEvent::PointerUp(event),
+                            },
+                        );
+                    }
+                    WindowEvent::Touch(touch) => {
+                        let contact =
+                            !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled);
+                        let button = match touch.phase {
+                            TouchPhase::Started | TouchPhase::Ended => Some(PointerButton::Primary),
+                            TouchPhase::Moved | TouchPhase::Cancelled => None,
+                        };
+                        // winit doesn't tell pens apart, but only the Apple Pencil reports an altitude.
+                        let (pointer_type, (tilt_x, tilt_y)) = match touch.force {
+                            Some(Force::Calibrated {
+                                altitude_angle: Some(altitude_angle),
+                                ..
+                            }) => (
+                                PointerType::Pen,
+                                pen_tilt(altitude_angle, PEN_AZIMUTH_ANGLE),
+                            ),
+                            _ => (PointerType::Touch, (0.0, 0.0)),
+                        };
+                        let event = PointerEvent {
+                            pointer_id: touch_pointer_id(touch.id),
+                            pointer_type,
+                            tilt_x,
+                            tilt_y,
+                            buttons: if contact {
+                                pointer_button_mask(PointerButton::Primary)
+                            } else {
+                                0
+                            },
+                            pressure: match (contact, touch.force) {
+                                (false, _) => 0.0,
+                                (true, Some(force)) => force.normalized() as f32,
+                                (true, None) => 0.5,
+                            },
+                            ..self.pointer_event(
+                                window_id,
+                                touch.location.x,
+                                touch.location.y,
+                                button,
+                            )
+                        };
+                        if let Some(proxy) = self.surface(window_id) {
+                            match touch.phase {
+                                TouchPhase::Started => proxy.pointer_down(event),
+                                TouchPhase::Moved => proxy.pointer_move(event),
+                                TouchPhase::Ended => proxy.pointer_up(event),
+                                TouchPhase::Cancelled => proxy.pointer_cancel(event),
+                            }
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(
+                            window_id,
+                            match touch.phase {
+                                TouchPhase::Started => InputEvent::PointerDown(event),
+                                TouchPhase::Moved => InputEvent::PointerMove(event),
+                                TouchPhase::Ended => InputEvent::PointerUp(event),
+                                TouchPhase::Cancelled => InputEvent::PointerCancel(event),
+                            },
+                        );
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        // winit's deltas are how far the content should move, the DOM's how far to scroll.
+                        let (delta_x, delta_y, delta_mode) = match delta {
+                            MouseScrollDelta::LineDelta(x, y) => {
+                                (-x as f64, -y as f64, WheelDeltaMode::Line)
+                            }
+                            MouseScrollDelta::PixelDelta(delta) => {
+                                (-delta.x, -delta.y, WheelDeltaMode::Pixel)
+                            }
+                        };
+                        self.wheel(window_id, delta_x, delta_y, delta_mode);
+                    }
+                    // two finger pan on touch screens, scrolls like a trackpad.
+                    WindowEvent::PanGesture { delta, .. } => {
+                        self.wheel(
+                            window_id,
+                            -delta.x as f64,
+                            -delta.y as f64,
+                            WheelDeltaMode::Pixel,
+                        );
+                    }
+                    WindowEvent::PinchGesture { delta, phase, .. } => {
+                        let (x, y) = *self.pointer_pos.get(&window_id).unwrap();
+                        let event = PinchEvent {
+                            x,
+                            y,
+                            delta,
+                            phase: phase.into(),
+                        };
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.pinch(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::Pinch(event));
+                    }
+                    WindowEvent::RotationGesture { delta, phase, .. } => {
+                        let (x, y) = *self.pointer_pos.get(&window_id).unwrap();
+                        let event = RotateEvent {
+                            x,
+                            y,
+                            delta: delta as f64,
+                            phase: phase.into(),
+                        };
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.rotate(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::Rotate(event));
+                    }
+                    WindowEvent::RedrawRequested => {
+                        let paused = self.frames.get(&window_id).is_none_or(|f| f.paused());
+                        if let Some(proxy) = self.surface(window_id).filter(|_| !paused) {
+                            proxy.animation_frame();
+                        }
+                    }
+                    WindowEvent::Occluded(occluded) => {
+                        self.update_frames(window_id, |frames| frames.occluded = occluded);
                     }
                     WindowEvent::Resized(new_size) => {
-                        if let Some(proxy) = self.surfaces.get(&window_id) {
-                            proxy.canvas_resize(ResizeEvent {
-                                height: new_size.height,
-                                width: new_size.width,
-                            });
+                        // some platforms resize minimized windows to zero instead of occluding them.
+                        self.update_frames(window_id, |frames| {
+                            frames.minimized = new_size.width == 0 || new_size.height == 0
+                        });
+                        let event = ResizeEvent {
+                            height: new_size.height,
+                            width: new_size.width,
+                        };
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.canvas_resize(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::Resize(event));
+                    }
+                    WindowEvent::Focused(focused) => {
+                        let event = FocusEvent { focused };
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.focus(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::Focus(event));
+                    }
+                    WindowEvent::CursorEntered { .. } => {
+                        let (pointer_x, pointer_y) = *self.pointer_pos.get(&window_id).unwrap();
+                        let event = self.pointer_event(window_id, pointer_x, pointer_y, None);
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.pointer_enter(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::PointerEnter(event));
+                    }
+                    WindowEvent::CursorLeft { .. } => {
+                        let (pointer_x, pointer_y) = *self.pointer_pos.get(&window_id).unwrap();
+                        let event = self.pointer_event(window_id, pointer_x, pointer_y, None);
+                        if let Some(proxy) = self.surface(window_id) {
+                            proxy.pointer_leave(event);
+                        }
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::PointerLeave(event));
+                    }
+                    WindowEvent::CloseRequested => {
+                        // the window is torn down when the guest drops the surface, not here.
+                        let handled = self
+                            .surface(window_id)
+                            .is_some_and(|proxy| proxy.close_requested());
+                        #[cfg(feature = "input-script")]
+                        self.record(window_id, InputEvent::CloseRequested);
+                        if !handled {
+                            // close it as far as the user can tell, the guest keeps running.
+                            if let Some(window) = self.windows.get(&window_id) {
+                                window.set_visible(false);
+                            }
+                            self.update_frames(window_id, |frames| frames.closed = true);
+                            if self.frames.values().all(|frames| frames.closed) {
+                                self.exit = Some(EventLoopExit::WindowsClosed);
+                                event_loop.exit();
+                            }
                         }
                     }
-                    WindowEvent::CloseRequested => std::process::exit(1),
                     _ => {}
                 }
             }
         }
 
         let mut app = App {
-            arc_surfaces: Arc::clone(&surfaces),
+            frame_rate: self.frame_rate,
+            proxy: Some(self.event_loop.create_proxy()),
+            #[cfg(feature = "input-script")]
+            recorder: self.recorder.clone(),
             ..Default::default()
         };
         self.event_loop.run_app(&mut app).unwrap();
+        app.exit.unwrap()
     }
 }
 
+/// Why [`WasiWinitEventLoop::run`] returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventLoopExit {
+    /// The user closed every window, none of them by a guest that handles close requests.
+    WindowsClosed,
+    /// [`WasiWinitEventLoopProxy::exit`] was called.
+    Requested,
+}
+
 #[derive(Clone)]
 pub struct WasiWinitEventLoopProxy {
     proxy: EventLoopProxy<MainThreadAction>,
@@ -233,6 +774,12 @@ impl WasiWinitEventLoopProxy {
             .unwrap();
         receiver.await.unwrap()
     }
+
+    /// Stop the event loop, e.g. once the guest returned. Windows still open are closed.
+    pub fn exit(&self) {
+        // fails if the event loop already exited.
+        let _ = self.proxy.send_event(MainThreadAction::Exit);
+    }
 }
 
 impl MainThreadSpawner for WasiWinitEventLoopProxy {
@@ -260,10 +807,13 @@ impl MainThreadSpawner for WasiWinitEventLoopProxy {
 
 enum MainThreadAction {
     CreateWindow(SurfaceDesc, oneshot::Sender<Surface>),
+    // sent when the surface backed by the window is dropped.
+    DestroyWindow(WindowId),
     Spawn(
         Box<dyn FnOnce() -> Box<dyn Any + Send> + Send>,
         oneshot::Sender<Box<dyn Any + Send>>,
     ),
+    Exit,
 }
 
 impl Debug for MainThreadAction {
@@ -274,7 +824,9 @@ impl Debug for MainThreadAction {
                 .field(arg0)
                 .field(arg1)
                 .finish(),
+            Self::DestroyWindow(arg0) => f.debug_tuple("DestroyWindow").field(arg0).finish(),
             Self::Spawn(_, _) => f.debug_tuple("Spawn").finish(),
+            Self::Exit => f.debug_tuple("Exit").finish(),
         }
     }
 }
@@ -483,3 +1035,104 @@ impl TryFrom<winit::keyboard::KeyCode> for wasi_gfx::surface::surface::Key {
         }
     }
 }
+
+impl From<TouchPhase> for GesturePhase {
+    fn from(value: TouchPhase) -> Self {
+        match value {
+            TouchPhase::Started => GesturePhase::Started,
+            TouchPhase::Moved => GesturePhase::Moved,
+            TouchPhase::Ended => GesturePhase::Ended,
+            TouchPhase::Cancelled => GesturePhase::Cancelled,
+        }
+    }
+}
+
+impl TryFrom<MouseButton> for PointerButton {
+    type Error = ();
+
+    fn try_from(value: MouseButton) -> Result<Self, Self::Error> {
+        match value {
+            MouseButton::Left => Ok(PointerButton::Primary),
+            MouseButton::Middle => Ok(PointerButton::Auxiliary),
+            MouseButton::Right => Ok(PointerButton::Secondary),
+            MouseButton::Back => Ok(PointerButton::Back),
+            MouseButton::Forward => Ok(PointerButton::Forward),
+            MouseButton::Other(_) => Err(()),
+        }
+    }
+}
+
+impl From<CursorGrab> for CursorGrabMode {
+    fn from(value: CursorGrab) -> Self {
+        match value {
+            CursorGrab::None => CursorGrabMode::None,
+            CursorGrab::Confined => CursorGrabMode::Confined,
+            CursorGrab::Locked => CursorGrabMode::Locked,
+        }
+    }
+}
+
+impl From<CursorIcon> for winit::window::CursorIcon {
+    fn from(value: CursorIcon) -> Self {
+        match value {
+            CursorIcon::Default => winit::window::CursorIcon::Default,
+            CursorIcon::ContextMenu => winit::window::CursorIcon::ContextMenu,
+            CursorIcon::Help => winit::window::CursorIcon::Help,
+            CursorIcon::Pointer => winit::window::CursorIcon::Pointer,
+            CursorIcon::Progress => winit::window::CursorIcon::Progress,
+            CursorIcon::Wait => winit::window::CursorIcon::Wait,
+            CursorIcon::Cell => winit::window::CursorIcon::Cell,
+            CursorIcon::Crosshair => winit::window::CursorIcon::Crosshair,
+            CursorIcon::Text => winit::window::CursorIcon::Text,
+            CursorIcon::VerticalText => winit::window::CursorIcon::VerticalText,
+            CursorIcon::Alias => winit::window::CursorIcon::Alias,
+            CursorIcon::Copy => winit::window::CursorIcon::Copy,
+            CursorIcon::Move => winit::window::CursorIcon::Move,
+            CursorIcon::NoDrop => winit::window::CursorIcon::NoDrop,
+            CursorIcon::NotAllowed => winit::window::CursorIcon::NotAllowed,
+            CursorIcon::Grab => winit::window::CursorIcon::Grab,
+            CursorIcon::Grabbing => winit::window::CursorIcon::Grabbing,
+            CursorIcon::EResize => winit::window::CursorIcon::EResize,
+            CursorIcon::NResize => winit::window::CursorIcon::NResize,
+            CursorIcon::NeResize => winit::window::CursorIcon::NeResize,
+            CursorIcon::NwResize => winit::window::CursorIcon::NwResize,
+            CursorIcon::SResize => winit::window::CursorIcon::SResize,
+            CursorIcon::SeResize => winit::window::CursorIcon::SeResize,
+            CursorIcon::SwResize => winit::window::CursorIcon::SwResize,
+            CursorIcon::WResize => winit::window::CursorIcon::WResize,
+            CursorIcon::EwResize => winit::window::CursorIcon::EwResize,
+            CursorIcon::NsResize => winit::window::CursorIcon::NsResize,
+            CursorIcon::NeswResize => winit::window::CursorIcon::NeswResize,
+            CursorIcon::NwseResize => winit::window::CursorIcon::NwseResize,
+            CursorIcon::ColResize => winit::window::CursorIcon::ColResize,
+            CursorIcon::RowResize => winit::window::CursorIcon::RowResize,
+            CursorIcon::AllScroll => winit::window::CursorIcon::AllScroll,
+            CursorIcon::ZoomIn => winit::window::CursorIcon::ZoomIn,
+            CursorIcon::ZoomOut => winit::window::CursorIcon::ZoomOut,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn assert_tilt(tilt: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (tilt.0 - expected.0).abs() < 1e-4 && (tilt.1 - expected.1).abs() < 1e-4,
+            "{tilt:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn pen_tilt_from_altitude() {
+        // upright.
+        assert_tilt(pen_tilt(FRAC_PI_2, 0.0), (0.0, 0.0));
+        assert_tilt(pen_tilt(FRAC_PI_4, 0.0), (45.0, 0.0));
+        assert_tilt(pen_tilt(FRAC_PI_4, FRAC_PI_2), (0.0, 45.0));
+        // lying flat.
+        assert_tilt(pen_tilt(0.0, 0.0), (90.0, 0.0));
+        assert_tilt(pen_tilt(0.0, FRAC_PI_4), (90.0, 90.0));
+    }
+}
@@ -0,0 +1,515 @@
+// Offscreen implementation of `GpuCanvasContext`.
+// https://www.w3.org/TR/webgpu/#canvas-context
+
+use std::{
+    slice,
+    sync::{mpsc, Arc},
+};
+
+use wasmtime::bail;
+
+use crate::{wasi::webgpu::webgpu, Device, Texture, ValidationError};
+
+// https://www.w3.org/TR/webgpu/#supported-context-formats
+pub const SUPPORTED_CONTEXT_FORMATS: [wgpu_types::TextureFormat; 3] = [
+    wgpu_types::TextureFormat::Bgra8Unorm,
+    wgpu_types::TextureFormat::Rgba8Unorm,
+    wgpu_types::TextureFormat::Rgba16Float,
+];
+
+/// Number of textures a [`CanvasContext`] cycles through.
+pub const FRAME_COUNT: usize = 3;
+
+/// A `GpuCanvasContext` that renders to offscreen textures instead of a window.
+///
+/// `wasi:webgpu` has no way for guests to create a canvas context, so embedders create one with [`CanvasContext::new`], push it into the resource table, and hand the resulting `Resource<GpuCanvasContext>` to the guest.
+///
+/// The context renders to a ring of [`FRAME_COUNT`] textures, like a swap chain.
+/// `get-current-texture` hands out the next texture in the ring, and presenting it makes it the latest presented frame without copying it.
+/// The embedder decides when a frame ends by calling [`CanvasContext::present`], and can read the latest presented frame back with [`CanvasContext::read_presented_frame`], or hold on to it with [`CanvasContext::presented_frame`] and read it back later.
+///
+/// Unlike the browser, presenting doesn't destroy the texture the guest rendered to, since it's reused for a later frame.
+/// A guest that keeps rendering to a texture after it's presented draws over that frame.
+pub struct CanvasContext {
+    width: u32,
+    height: u32,
+    configuration: Option<CanvasConfiguration>,
+}
+
+/// Configuration of a [`CanvasContext`], as passed to `configure`.
+pub struct CanvasConfiguration {
+    pub device: Device,
+    pub format: webgpu::GpuTextureFormat,
+    pub usage: Option<webgpu::GpuTextureUsage>,
+    pub view_formats: Option<Vec<webgpu::GpuTextureFormat>>,
+    pub color_space: Option<webgpu::PredefinedColorSpace>,
+    pub tone_mapping: Option<webgpu::GpuCanvasToneMapping>,
+    pub alpha_mode: Option<webgpu::GpuCanvasAlphaMode>,
+    // descriptor of the textures handed out by `get_current_texture`, as the guest sees them.
+    descriptor: wgpu_types::TextureDescriptor<(), Vec<wgpu_types::TextureFormat>>,
+    frames: Vec<Arc<SharedTextureId>>,
+    // index into `frames` of the texture handed out since the last present, if any.
+    current_frame: Option<usize>,
+    // index into `frames` of the latest presented frame, `None` until the first present.
+    presented_frame: Option<usize>,
+}
+
+/// A frame read back from a [`CanvasContext`].
+#[derive(Clone, Debug)]
+pub struct CanvasFrame {
+    pub width: u32,
+    pub height: u32,
+    /// One of the formats a canvas can be configured with, i.e. `Bgra8Unorm`, `Rgba8Unorm`, or `Rgba16Float`.
+    pub format: wgpu_types::TextureFormat,
+    /// Tightly packed rows of texels, top row first.
+    pub data: Vec<u8>,
+}
+
+/// A frame presented by a [`CanvasContext`], still on the GPU.
+///
+/// Holds on to the frame's texture and device, so it can be read back after the context moved on, or was reconfigured.
+/// The context doesn't hand out the latest presented frame again, so it reads back as presented for as long as it stays the latest one.
+#[derive(Clone)]
+pub struct PresentedCanvasFrame(Arc<PresentedCanvasFrameInner>);
+
+struct PresentedCanvasFrameInner {
+    instance: Arc<wgpu_core::global::Global>,
+    // only `None` while dropping, see `Device::release`.
+    device: Option<Device>,
+    // `None` if nothing was presented, which reads back as a cleared frame.
+    texture: Option<Arc<SharedTextureId>>,
+    descriptor: wgpu_types::TextureDescriptor<(), Vec<wgpu_types::TextureFormat>>,
+    alpha_mode: Option<webgpu::GpuCanvasAlphaMode>,
+}
+impl Drop for PresentedCanvasFrameInner {
+    fn drop(&mut self) {
+        if let Some(device) = self.device.take() {
+            device.release(&self.instance);
+        }
+    }
+}
+
+impl std::fmt::Debug for PresentedCanvasFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PresentedCanvasFrame")
+            .field("descriptor", &self.0.descriptor)
+            .field("alpha_mode", &self.0.alpha_mode)
+            .finish_non_exhaustive()
+    }
+}
+
+// The textures of a canvas are both owned by the context, and by every `GpuTexture` handed out for them.
+// The guest can keep using a `GpuTexture` after the context is done with it, so the id is only dropped once neither refers to it anymore.
+pub(crate) struct SharedTextureId {
+    instance: Arc<wgpu_core::global::Global>,
+    pub(crate) id: wgpu_core::id::TextureId,
+}
+impl Drop for SharedTextureId {
+    fn drop(&mut self) {
+        self.instance.texture_drop(self.id);
+    }
+}
+
+impl CanvasContext {
+    /// Create an unconfigured canvas context, with a canvas of `width` by `height` pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            configuration: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Resize the canvas.
+    ///
+    /// Like resizing a canvas in the browser, this replaces the frames with cleared ones, and expires the current texture.
+    pub fn resize(&mut self, instance: &Arc<wgpu_core::global::Global>, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        if let Some(configuration) = &mut self.configuration {
+            configuration.descriptor.size = wgpu_types::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            };
+            configuration.destroy_frames(instance);
+            configuration.frames =
+                create_frames(instance, &configuration.device, &configuration.descriptor);
+        }
+    }
+
+    /// Present the current texture, if the guest asked for one since the last call to `present`.
+    ///
+    /// The texture becomes the latest presented frame, and the next call to `get-current-texture` returns the next texture in the ring.
+    /// If there is no current texture, the previously presented frame stays the latest one.
+    ///
+    /// https://www.w3.org/TR/webgpu/#abstract-opdef-expire-the-current-texture
+    pub fn present(&mut self) {
+        let Some(configuration) = &mut self.configuration else {
+            return;
+        };
+        if let Some(current_frame) = configuration.current_frame.take() {
+            configuration.presented_frame = Some(current_frame);
+        }
+    }
+
+    /// The latest presented frame, to be read back later with [`PresentedCanvasFrame::read`].
+    ///
+    /// Returns `None` if the context isn't configured. If nothing was presented since it was configured or resized, the frame reads back cleared.
+    pub fn presented_frame(
+        &self,
+        instance: &Arc<wgpu_core::global::Global>,
+    ) -> Option<PresentedCanvasFrame> {
+        let configuration = self.configuration.as_ref()?;
+        Some(PresentedCanvasFrame(Arc::new(PresentedCanvasFrameInner {
+            instance: Arc::clone(instance),
+            device: Some(configuration.device.clone()),
+            texture: configuration
+                .presented_frame
+                .map(|presented_frame| Arc::clone(&configuration.frames[presented_frame])),
+            descriptor: configuration.descriptor.clone(),
+            alpha_mode: configuration.alpha_mode,
+        })))
+    }
+
+    /// Read back the latest presented frame.
+    ///
+    /// Returns `None` if the context isn't configured, and a cleared frame if nothing was presented since it was configured or resized.
+    /// Blocks until the GPU is done with the frame.
+    ///
+    /// If the canvas is configured with an `opaque` alpha mode, the alpha channel of the returned frame is set to 1, since that's how the frame would be shown.
+    pub fn read_presented_frame(
+        &self,
+        instance: &Arc<wgpu_core::global::Global>,
+    ) -> wasmtime::Result<Option<CanvasFrame>> {
+        self.presented_frame(instance)
+            .map(|frame| frame.read())
+            .transpose()
+    }
+
+    /// Configure the context, as in `GpuCanvasContext.configure`. `device` is the device `configuration.device` refers to.
+    ///
+    /// The browser throws a `TypeError` for an unsupported format or usage, which `configure` can't return.
+    /// Those are reported to `device` as validation errors instead, and leave the context as it was.
+    ///
+    /// https://www.w3.org/TR/webgpu/#dom-gpucanvascontext-configure
+    pub fn configure(
+        &mut self,
+        instance: &Arc<wgpu_core::global::Global>,
+        device: Device,
+        configuration: webgpu::GpuCanvasConfiguration,
+    ) {
+        let format: wgpu_types::TextureFormat = configuration.format.into();
+        if !SUPPORTED_CONTEXT_FORMATS.contains(&format) {
+            return reject_configuration(
+                instance,
+                device,
+                format!("{format:?} is not a supported canvas format"),
+            );
+        }
+        let Ok(usage) = configuration
+            .usage
+            .unwrap_or(webgpu::GpuTextureUsage::RENDER_ATTACHMENT)
+            .try_into()
+        else {
+            return reject_configuration(
+                instance,
+                device,
+                "unsupported canvas texture usage".to_string(),
+            );
+        };
+
+        self.unconfigure(instance);
+
+        let descriptor = wgpu_types::TextureDescriptor {
+            label: (),
+            size: wgpu_types::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu_types::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: configuration
+                .view_formats
+                .iter()
+                .flatten()
+                .map(|&view_format| view_format.into())
+                .collect(),
+        };
+        let frames = create_frames(instance, &device, &descriptor);
+        self.configuration = Some(CanvasConfiguration {
+            device,
+            format: configuration.format,
+            usage: configuration.usage,
+            view_formats: configuration.view_formats,
+            color_space: configuration.color_space,
+            tone_mapping: configuration.tone_mapping,
+            alpha_mode: configuration.alpha_mode,
+            descriptor,
+            frames,
+            current_frame: None,
+            presented_frame: None,
+        });
+    }
+
+    /// https://www.w3.org/TR/webgpu/#dom-gpucanvascontext-unconfigure
+    pub fn unconfigure(&mut self, instance: &Arc<wgpu_core::global::Global>) {
+        if let Some(mut configuration) = self.configuration.take() {
+            configuration.destroy_frames(instance);
+            configuration.device.release(instance);
+        }
+    }
+
+    /// The configuration passed to `configure`, if configured.
+    pub fn configuration(&self) -> Option<&CanvasConfiguration> {
+        self.configuration.as_ref()
+    }
+
+    /// https://www.w3.org/TR/webgpu/#dom-gpucanvascontext-getcurrenttexture
+    pub fn get_current_texture(&mut self) -> wasmtime::Result<Texture> {
+        let Some(configuration) = &mut self.configuration else {
+            bail!("InvalidStateError: canvas context is not configured");
+        };
+        let current_frame = *configuration.current_frame.get_or_insert_with(|| {
+            // the frame after the latest presented one is the oldest in the ring.
+            configuration
+                .presented_frame
+                .map_or(0, |presented_frame| (presented_frame + 1) % FRAME_COUNT)
+        });
+        let current_texture = Arc::clone(&configuration.frames[current_frame]);
+        Ok(Texture {
+            texture_id: current_texture.id,
+            label: String::new(),
+            descriptor: configuration.descriptor.clone(),
+            texture_binding_view_dimension: None,
+            error_handler: Arc::clone(&configuration.device.error_handler),
+            shared_id: Some(current_texture),
+        })
+    }
+}
+
+impl CanvasConfiguration {
+    // Destroys the frames, which also expires the current texture.
+    // The latest presented frame is left for `PresentedCanvasFrame`s that still refer to it, and dropped once they're gone.
+    fn destroy_frames(&mut self, instance: &wgpu_core::global::Global) {
+        for (index, frame) in self.frames.drain(..).enumerate() {
+            if Some(index) != self.presented_frame {
+                instance.texture_destroy(frame.id);
+            }
+        }
+        self.current_frame = None;
+        self.presented_frame = None;
+    }
+}
+
+impl PresentedCanvasFrame {
+    /// Read the frame back. Blocks until the GPU is done with the frame.
+    ///
+    /// If the canvas is configured with an `opaque` alpha mode, the alpha channel of the returned frame is set to 1, since that's how the frame would be shown.
+    pub fn read(&self) -> wasmtime::Result<CanvasFrame> {
+        let PresentedCanvasFrameInner {
+            instance,
+            device: Some(device),
+            texture,
+            descriptor,
+            ..
+        } = &*self.0
+        else {
+            unreachable!("the device is only taken when dropped")
+        };
+        let wgpu_types::Extent3d { width, height, .. } = descriptor.size;
+        let format = descriptor.format;
+        // all supported context formats are uncompressed color formats.
+        let bytes_per_texel = format.block_copy_size(None).unwrap();
+        let unpadded_bytes_per_row = width * bytes_per_texel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu_types::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let size = padded_bytes_per_row as u64 * height as u64;
+
+        let Some(texture) = texture else {
+            let mut data = vec![0; (unpadded_bytes_per_row * height) as usize];
+            self.make_opaque(&mut data);
+            return Ok(CanvasFrame {
+                width,
+                height,
+                format,
+                data,
+            });
+        };
+        let (buffer, err) = instance.device_create_buffer(
+            device.device,
+            &wgpu_types::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgpu_types::BufferUsages::MAP_READ | wgpu_types::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+            None,
+        );
+        let result = (|| -> wasmtime::Result<_> {
+            if let Some(err) = err {
+                return Err(err.into());
+            }
+            let submission_index = submit(instance, device, |encoder| {
+                instance.command_encoder_copy_texture_to_buffer(
+                    encoder,
+                    &wgpu_types::TexelCopyTextureInfo {
+                        texture: texture.id,
+                        mip_level: 0,
+                        origin: wgpu_types::Origin3d::ZERO,
+                        aspect: wgpu_types::TextureAspect::All,
+                    },
+                    &wgpu_types::TexelCopyBufferInfo {
+                        buffer,
+                        layout: wgpu_types::TexelCopyBufferLayout {
+                            offset: 0,
+                            bytes_per_row: Some(padded_bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    &descriptor.size,
+                )?;
+                Ok(())
+            })?;
+
+            let (sender, receiver) = mpsc::channel();
+            instance.buffer_map_async(
+                buffer,
+                0,
+                Some(size),
+                wgpu_core::resource::BufferMapOperation {
+                    host: wgpu_core::device::HostMap::Read,
+                    callback: Some(Box::new(move |result| {
+                        let _ = sender.send(result);
+                    })),
+                },
+            )?;
+            instance.device_poll(
+                device.device,
+                wgpu_types::PollType::Wait {
+                    submission_index: Some(submission_index),
+                    timeout: None,
+                },
+            )?;
+            // the callback might be called by the device's poller instead, in which case this waits for it.
+            receiver.recv()??;
+
+            let (ptr, _) = instance.buffer_get_mapped_range(buffer, 0, Some(size))?;
+            let mapped = unsafe { slice::from_raw_parts(ptr.as_ptr(), size as usize) };
+            let mut data = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+            for row in mapped.chunks_exact(padded_bytes_per_row as usize) {
+                data.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+            instance.buffer_unmap(buffer)?;
+            Ok(data)
+        })();
+        instance.buffer_drop(buffer);
+        let mut data = result?;
+        self.make_opaque(&mut data);
+
+        Ok(CanvasFrame {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    // https://www.w3.org/TR/webgpu/#dom-gpucanvasalphamode-opaque
+    fn make_opaque(&self, data: &mut [u8]) {
+        if self
+            .0
+            .alpha_mode
+            .unwrap_or(webgpu::GpuCanvasAlphaMode::Opaque)
+            != webgpu::GpuCanvasAlphaMode::Opaque
+        {
+            return;
+        }
+        let format = self.0.descriptor.format;
+        let opaque_alpha: &[u8] = match format {
+            // 1.0 as a little endian f16.
+            wgpu_types::TextureFormat::Rgba16Float => &[0x00, 0x3c],
+            _ => &[0xff],
+        };
+        // all supported context formats are uncompressed color formats.
+        let bytes_per_texel = format.block_copy_size(None).unwrap();
+        for texel in data.chunks_exact_mut(bytes_per_texel as usize) {
+            let alpha_start = texel.len() - opaque_alpha.len();
+            texel[alpha_start..].copy_from_slice(opaque_alpha);
+        }
+    }
+}
+
+// Records commands with `encode`, and submits them to the device's queue.
+fn submit(
+    instance: &wgpu_core::global::Global,
+    device: &Device,
+    encode: impl FnOnce(wgpu_core::id::CommandEncoderId) -> wasmtime::Result<()>,
+) -> wasmtime::Result<wgpu_core::SubmissionIndex> {
+    let (encoder, err) = instance.device_create_command_encoder(
+        device.device,
+        &wgpu_types::CommandEncoderDescriptor { label: None },
+        None,
+    );
+    let result = (|| -> wasmtime::Result<_> {
+        if let Some(err) = err {
+            return Err(err.into());
+        }
+        encode(encoder)?;
+        let (command_buffer, err) = instance.command_encoder_finish(
+            encoder,
+            &wgpu_types::CommandBufferDescriptor { label: None },
+            None,
+        );
+        let result = match err {
+            Some((_, err)) => Err(err.into()),
+            None => instance
+                .queue_submit(*device.queue, &[command_buffer])
+                .map_err(|(_, err)| err.into()),
+        };
+        instance.command_buffer_drop(command_buffer);
+        result
+    })();
+    instance.command_encoder_drop(encoder);
+    result
+}
+
+// `COPY_SRC` is added to the usage the guest asked for, to read presented frames back.
+fn create_frames(
+    instance: &Arc<wgpu_core::global::Global>,
+    device: &Device,
+    descriptor: &wgpu_types::TextureDescriptor<(), Vec<wgpu_types::TextureFormat>>,
+) -> Vec<Arc<SharedTextureId>> {
+    let mut descriptor = descriptor.map_label(|_| None);
+    descriptor.usage |= wgpu_types::TextureUsages::COPY_SRC;
+    (0..FRAME_COUNT)
+        .map(|_| {
+            let (id, err) = instance.device_create_texture(device.device, &descriptor, None);
+            device.error_handler.handle_possible_error(err);
+            Arc::new(SharedTextureId {
+                instance: Arc::clone(instance),
+                id,
+            })
+        })
+        .collect()
+}
+
+// Reports a configuration the browser would throw a `TypeError` for, and releases the device it was for.
+fn reject_configuration(instance: &wgpu_core::global::Global, device: Device, message: String) {
+    device
+        .error_handler
+        .handle_possible_error(Some(ValidationError(message)));
+    device.release(instance);
+}
@@ -281,6 +281,16 @@ impl From<webgpu::GpuTextureDimension> for wgpu_types::TextureDimension {
     }
 }
 
+impl From<wgpu_types::TextureDimension> for webgpu::GpuTextureDimension {
+    fn from(value: wgpu_types::TextureDimension) -> Self {
+        match value {
+            wgpu_types::TextureDimension::D1 => webgpu::GpuTextureDimension::D1,
+            wgpu_types::TextureDimension::D2 => webgpu::GpuTextureDimension::D2,
+            wgpu_types::TextureDimension::D3 => webgpu::GpuTextureDimension::D3,
+        }
+    }
+}
+
 impl From<webgpu::GpuAddressMode> for wgpu_types::AddressMode {
     fn from(value: webgpu::GpuAddressMode) -> Self {
         match value {
@@ -597,3 +607,12 @@ impl From<webgpu::GpuCanvasAlphaMode> for wgpu_types::CompositeAlphaMode {
         }
     }
 }
+
+impl From<wgpu_types::DeviceLostReason> for webgpu::GpuDeviceLostReason {
+    fn from(value: wgpu_types::DeviceLostReason) -> Self {
+        match value {
+            wgpu_types::DeviceLostReason::Unknown => webgpu::GpuDeviceLostReason::Unknown,
+            wgpu_types::DeviceLostReason::Destroyed => webgpu::GpuDeviceLostReason::Destroyed,
+        }
+    }
+}
@@ -18,8 +18,13 @@ mod enum_conversions;
 mod flags_conversions;
 mod to_core_conversions;
 
+mod canvas_context;
 mod trait_impls;
 mod types;
+pub use canvas_context::{
+    CanvasConfiguration, CanvasContext, CanvasFrame, PresentedCanvasFrame, FRAME_COUNT,
+    SUPPORTED_CONTEXT_FORMATS,
+};
 pub use types::*;
 
 /// Re-export of `wgpu_core` and `wgpu_types` so that runtime implementors don't need to keep track of what version of wgpu this crate is using.
@@ -55,28 +60,32 @@ wasmtime::component::bindgen!({
     with: {
         "wasi:webgpu/webgpu.gpu-adapter": types::Adapter,
         "wasi:webgpu/webgpu.gpu-device": types::Device,
+        "wasi:webgpu/webgpu.gpu-canvas-context": canvas_context::CanvasContext,
         "wasi:webgpu/webgpu.gpu-queue": types::Queue,
         "wasi:webgpu/webgpu.gpu-command-encoder": types::CommandEncoder,
         "wasi:webgpu/webgpu.gpu-render-pass-encoder": types::RenderPassEncoder,
         "wasi:webgpu/webgpu.gpu-compute-pass-encoder": types::ComputePassEncoder,
-        "wasi:webgpu/webgpu.gpu-shader-module": wgpu_core::id::ShaderModuleId,
+        "wasi:webgpu/webgpu.gpu-shader-module": types::ShaderModule,
         "wasi:webgpu/webgpu.gpu-render-pipeline": types::RenderPipeline,
         "wasi:webgpu/webgpu.gpu-render-bundle-encoder": types::RenderBundleEncoder,
-        "wasi:webgpu/webgpu.gpu-render-bundle": wgpu_core::id::RenderBundleId,
-        "wasi:webgpu/webgpu.gpu-command-buffer": wgpu_core::id::CommandBufferId,
+        "wasi:webgpu/webgpu.gpu-render-bundle": types::RenderBundle,
+        "wasi:webgpu/webgpu.gpu-command-buffer": types::CommandBuffer,
         "wasi:webgpu/webgpu.gpu-buffer": types::Buffer,
-        "wasi:webgpu/webgpu.gpu-pipeline-layout": wgpu_core::id::PipelineLayoutId,
-        "wasi:webgpu/webgpu.gpu-bind-group-layout": wgpu_core::id::BindGroupLayoutId,
-        "wasi:webgpu/webgpu.gpu-sampler": wgpu_core::id::SamplerId,
+        "wasi:webgpu/webgpu.gpu-pipeline-layout": types::PipelineLayout,
+        "wasi:webgpu/webgpu.gpu-bind-group-layout": types::BindGroupLayout,
+        "wasi:webgpu/webgpu.gpu-sampler": types::Sampler,
         "wasi:webgpu/webgpu.gpu-supported-features": wgpu_types::Features,
         "wasi:webgpu/webgpu.gpu-texture": types::Texture,
         "wasi:webgpu/webgpu.gpu-compute-pipeline": types::ComputePipeline,
-        "wasi:webgpu/webgpu.gpu-bind-group": wgpu_core::id::BindGroupId,
-        "wasi:webgpu/webgpu.gpu-texture-view": wgpu_core::id::TextureViewId,
+        "wasi:webgpu/webgpu.gpu-bind-group": types::BindGroup,
+        "wasi:webgpu/webgpu.gpu-texture-view": types::TextureView,
         "wasi:webgpu/webgpu.gpu-adapter-info": wgpu_types::AdapterInfo,
-        "wasi:webgpu/webgpu.gpu-query-set": wgpu_core::id::QuerySetId,
+        "wasi:webgpu/webgpu.gpu-query-set": types::QuerySet,
         "wasi:webgpu/webgpu.gpu-supported-limits": wgpu_types::Limits,
         "wasi:webgpu/webgpu.gpu-error": types::GpuError,
+        "wasi:webgpu/webgpu.gpu-device-lost-info": types::DeviceLostInfo,
+        "wasi:webgpu/webgpu.gpu-compilation-info": types::CompilationInfo,
+        "wasi:webgpu/webgpu.gpu-compilation-message": types::CompilationMessage,
         "wasi:webgpu/webgpu.wgsl-language-features": types::WgslLanguageFeatures,
         "wasi:webgpu/webgpu.record-gpu-pipeline-constant-value": types::RecordGpuPipelineConstantValue,
         "wasi:webgpu/webgpu.record-option-gpu-size64": types::RecordOptionGpuSize64,
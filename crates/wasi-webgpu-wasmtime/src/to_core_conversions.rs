@@ -15,6 +15,29 @@ where
     }
 }
 
+impl<T> ToCore<T> for wasmtime::component::Resource<crate::types::Labeled<T>>
+where
+    T: Copy + 'static,
+{
+    fn to_core(self, table: &ResourceTable) -> T {
+        table.get(&self).unwrap().id
+    }
+}
+
+impl ToCore<wgpu_core::id::ShaderModuleId>
+    for wasmtime::component::Resource<crate::types::ShaderModule>
+{
+    fn to_core(self, table: &ResourceTable) -> wgpu_core::id::ShaderModuleId {
+        table.get(&self).unwrap().id
+    }
+}
+
+impl ToCore<wgpu_core::id::QuerySetId> for wasmtime::component::Resource<crate::types::QuerySet> {
+    fn to_core(self, table: &ResourceTable) -> wgpu_core::id::QuerySetId {
+        table.get(&self).unwrap().id
+    }
+}
+
 impl ToCore<wgpu_types::RequestAdapterOptions<wgpu_core::id::SurfaceId>>
     for webgpu::GpuRequestAdapterOptions
 {
@@ -119,12 +142,12 @@ impl<'a> ToCore<wgpu_core::binding_model::PipelineLayoutDescriptor<'a>>
                 .into_iter()
                 .map(|bind_group_layout| {
                     bind_group_layout
-                        .map(|bind_group_layout| *table.get(&bind_group_layout).unwrap())
+                        .map(|bind_group_layout| table.get(&bind_group_layout).unwrap().id)
                 })
                 .collect::<Vec<_>>()
                 .into(),
-            // immediate_size is not present in WebGPU
-            immediate_size: 0,
+            // https://www.w3.org/TR/webgpu/#dom-gpupipelinelayoutdescriptor-immediatesize
+            immediate_size: self.immediate_size.unwrap_or(0),
         }
     }
 }
@@ -654,16 +677,18 @@ impl<'a> ToCore<wgpu_types::DeviceDescriptor<wgpu_core::Label<'a>>>
     for webgpu::GpuDeviceDescriptor
 {
     fn to_core(self, table: &ResourceTable) -> wgpu_types::DeviceDescriptor<wgpu_core::Label<'a>> {
+        let required_features: wgpu_types::Features = self
+            .required_features
+            .map(|f| f.to_core(table))
+            .unwrap_or_default();
+        let required_limits = self
+            .required_limits
+            .map(|limit| table.get(&limit).unwrap().to_core(table))
+            .unwrap_or(wgpu_types::Limits::defaults());
         wgpu_types::DeviceDescriptor {
             label: self.label.map(|l| l.into()),
-            required_features: self
-                .required_features
-                .map(|f| f.to_core(table))
-                .unwrap_or_default(),
-            required_limits: self
-                .required_limits
-                .map(|limit| table.get(&limit).unwrap().to_core(table))
-                .unwrap_or(wgpu_types::Limits::defaults()),
+            required_features,
+            required_limits,
             // TODO: use self.default_queue?
             // memory_hints is not present in WebGPU
             memory_hints: wgpu_types::MemoryHints::default(),
@@ -1126,7 +1151,7 @@ impl<'a> ToCore<wgpu_types::QuerySetDescriptor<wgpu_core::Label<'a>>>
             ty: match self.type_ {
                 webgpu::GpuQueryType::Occlusion => wgpu_types::QueryType::Occlusion,
                 webgpu::GpuQueryType::Timestamp => wgpu_types::QueryType::Timestamp,
-                // TODO: Why is PipelineStatistics missing?
+                // pipeline statistics queries were dropped from WebGPU, wgpu only has them as a native-only feature.
             },
             count: self.count,
         }
@@ -1,18 +1,16 @@
 use callback_future::CallbackFuture;
 use core::slice;
-use shared::StreamPipeMap;
+use shared::{FuturePipeMap, StreamPipeMap};
 use std::{borrow::Cow, collections::HashMap, num::NonZeroU64, sync::Arc};
-use wasmtime::{
-    bail,
-    component::{Access, Accessor, FutureReader, Resource, StreamReader},
-};
+use wasmtime::component::{Access, Accessor, FutureReader, Resource, StreamReader};
 
 use crate::{
     to_core_conversions::ToCore,
     types::{
-        Buffer, CommandEncoder, ComputePassEncoder, ComputePipeline, Device, ErrorHandler,
-        RenderBundleEncoder, RenderBundleEncoderInner, RenderPassEncoder, RenderPipeline, Texture,
-        WgslLanguageFeatures,
+        BindGroup, BindGroupLayout, Buffer, CommandBuffer, CommandEncoder, CompilationInfo,
+        ComputePassEncoder, ComputePipeline, Device, LabeledError, PipelineLayout, QuerySet, Queue,
+        RenderBundle, RenderBundleEncoder, RenderPassEncoder, RenderPipeline, Sampler,
+        ShaderModule, Texture, TextureView, ValidationError, WgslLanguageFeatures,
     },
     wasi::webgpu::webgpu,
     WasiWebGpuCtx, WasiWebGpuCtxView, PREFERRED_CANVAS_FORMAT,
@@ -191,6 +189,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
 
         let (command_encoder_id, err) = self.instance.device_create_command_encoder(
             device_id,
@@ -204,6 +203,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         let command_encoder = self.table.push(CommandEncoder {
             command_encoder_id,
+            label: label.unwrap_or_default(),
             error_handler,
         })?;
         Ok(command_encoder)
@@ -218,6 +218,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
 
+        let label = descriptor.label.clone();
         let code =
             wgpu_core::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(descriptor.code.to_owned()));
         let (shader, err) = self.instance.device_create_shader_module(
@@ -227,9 +228,17 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
             None,
         );
 
+        let compilation_info = err
+            .as_ref()
+            .map(CompilationInfo::from_error)
+            .unwrap_or_default();
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(shader)?)
+        Ok(self.table.push(ShaderModule {
+            id: shader,
+            label: label.unwrap_or_default(),
+            compilation_info,
+        })?)
     }
 
     fn create_render_pipeline(
@@ -240,6 +249,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.label.clone();
 
         let (render_pipeline_id, err) = self.instance.device_create_render_pipeline(
             device_id,
@@ -251,13 +261,20 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         let render_pipeline = self.table.push(RenderPipeline {
             render_pipeline_id,
+            label: label.unwrap_or_default(),
             error_handler,
         })?;
         Ok(render_pipeline)
     }
 
     fn queue(&mut self, device: Resource<Device>) -> wasmtime::Result<Resource<webgpu::GpuQueue>> {
-        let queue = Arc::clone(&self.table.get(&device)?.queue);
+        let device = self.table.get(&device)?;
+        let queue = Queue {
+            id: Arc::clone(&device.queue),
+            poller: Arc::clone(&device.poller),
+            error_handler: Arc::clone(&device.error_handler),
+            label: Arc::clone(&device.queue_label),
+        };
         Ok(self.table.push(queue)?)
     }
 
@@ -282,6 +299,13 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
     fn destroy(&mut self, device: Resource<webgpu::GpuDevice>) -> wasmtime::Result<()> {
         let device_id = self.table.get(&device)?.device;
         self.instance.device_destroy(device_id);
+        // wgpu-core only calls the device lost closure from poll, once the queue is empty.
+        if let Err(e) = self
+            .instance
+            .device_poll(device_id, wgpu_types::PollType::Poll)
+        {
+            log::warn!("device poll after destroy failed: {e}");
+        }
         Ok(())
     }
 
@@ -293,6 +317,8 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let poller = Arc::clone(&device.poller);
+        let label = descriptor.label.clone();
         let descriptor = descriptor.to_core(self.table);
 
         let size = descriptor.size;
@@ -310,9 +336,12 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         let buffer = Buffer {
             buffer_id,
+            label: label.unwrap_or_default(),
             size,
             usage,
             map_state,
+            poller,
+            error_handler,
         };
 
         Ok(self.table.push(buffer)?)
@@ -327,15 +356,23 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
 
-        let (texture_id, err) =
-            self.instance
-                .device_create_texture(device_id, &descriptor.to_core(self.table), None);
+        let label = descriptor.label.clone();
+        let texture_binding_view_dimension = descriptor.texture_binding_view_dimension;
+        let descriptor = descriptor.to_core(self.table);
+
+        let (texture_id, err) = self
+            .instance
+            .device_create_texture(device_id, &descriptor, None);
 
         error_handler.handle_possible_error(err);
 
         Ok(self.table.push(Texture {
             texture_id,
+            label: label.unwrap_or_default(),
+            descriptor: descriptor.map_label(|_| ()),
+            texture_binding_view_dimension,
             error_handler,
+            shared_id: None,
         })?)
     }
 
@@ -347,6 +384,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
 
         let descriptor = descriptor
             .map(|d| d.to_core(self.table))
@@ -372,7 +410,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(sampler)?)
+        Ok(self.table.push(Sampler::new(sampler, label))?)
     }
 
     fn create_bind_group_layout(
@@ -383,6 +421,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.label.clone();
 
         let (bind_group_layout, err) = self.instance.device_create_bind_group_layout(
             device_id,
@@ -392,7 +431,9 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(bind_group_layout)?)
+        Ok(self
+            .table
+            .push(BindGroupLayout::new(bind_group_layout, label))?)
     }
 
     fn create_pipeline_layout(
@@ -403,6 +444,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.label.clone();
 
         let (pipeline_layout, err) = self.instance.device_create_pipeline_layout(
             device_id,
@@ -412,7 +454,9 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(pipeline_layout)?)
+        Ok(self
+            .table
+            .push(PipelineLayout::new(pipeline_layout, label))?)
     }
 
     fn create_bind_group(
@@ -424,6 +468,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
 
+        let label = descriptor.label.clone();
         // not using to_core for conversion since we need instance or self for `GpuBindingResource::GpuTexture`
         let descriptor = wgpu_core::binding_model::BindGroupDescriptor {
             label: descriptor.label.map(|l| l.into()),
@@ -457,7 +502,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
                         webgpu::GpuBindingResource::GpuTexture(texture) => {
                             let view =
                                 webgpu::HostGpuTexture::create_view(self, texture, None).unwrap();
-                            let view = *self.table.get(&view).unwrap();
+                            let view = self.table.get(&view).unwrap().id;
                             wgpu_core::binding_model::BindingResource::TextureView(view)
                         }
                         webgpu::GpuBindingResource::GpuTextureView(texture_view) => {
@@ -476,7 +521,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(bind_group)?)
+        Ok(self.table.push(BindGroup::new(bind_group, label))?)
     }
 
     fn create_compute_pipeline(
@@ -487,6 +532,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.label.clone();
 
         let (compute_pipeline_id, err) = self.instance.device_create_compute_pipeline(
             device_id,
@@ -498,6 +544,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         Ok(self.table.push(ComputePipeline {
             compute_pipeline_id,
+            label: label.unwrap_or_default(),
             error_handler,
         })?)
     }
@@ -510,16 +557,22 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
-        let render_bundle_encoder = wgpu_core::command::RenderBundleEncoder::new(
+        let label = descriptor.label.clone();
+        let render_bundle_encoder = match wgpu_core::command::RenderBundleEncoder::new(
             &descriptor.to_core(self.table),
             device_id,
-        )?;
-        let render_bundle_encoder =
-            self.table
-                .push(RenderBundleEncoder::new(RenderBundleEncoderInner {
-                    render_bundle_encoder,
-                    error_handler,
-                }))?;
+        ) {
+            Ok(render_bundle_encoder) => render_bundle_encoder,
+            Err(err) => {
+                error_handler.handle_possible_error(Some(err));
+                wgpu_core::command::RenderBundleEncoder::dummy(device_id)
+            }
+        };
+        let render_bundle_encoder = self.table.push(RenderBundleEncoder::new(
+            render_bundle_encoder,
+            label,
+            error_handler,
+        ))?;
         Ok(render_bundle_encoder)
     }
 
@@ -531,6 +584,9 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
         let device = self.table.get(&device)?;
         let device_id = device.device;
         let error_handler = Arc::clone(&device.error_handler);
+        let label = descriptor.label.clone();
+        let type_ = descriptor.type_;
+        let count = descriptor.count;
 
         let (query_set, err) =
             self.instance
@@ -538,19 +594,26 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(Ok(self.table.push(query_set)?))
+        Ok(Ok(self.table.push(QuerySet {
+            id: query_set,
+            destroyed: false,
+            label: label.unwrap_or_default(),
+            type_,
+            count,
+        })?))
     }
 
-    fn label(&mut self, _device: Resource<webgpu::GpuDevice>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, device: Resource<webgpu::GpuDevice>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&device)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _device: Resource<webgpu::GpuDevice>,
-        _label: String,
+        device: Resource<webgpu::GpuDevice>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&device)?.label = label;
+        Ok(())
     }
 
     fn push_error_scope(
@@ -565,13 +628,7 @@ impl<'a> webgpu::HostGpuDevice for WasiWebGpuCtx<'a> {
 
     fn drop(&mut self, device: Resource<webgpu::GpuDevice>) -> wasmtime::Result<()> {
         let device = self.table.delete(device)?;
-        self.instance.device_drop(device.device);
-        if let Some(adapter_id) = Arc::into_inner(device.adapter) {
-            self.instance.adapter_drop(adapter_id);
-        }
-        if let Some(queue_id) = Arc::into_inner(device.queue) {
-            self.instance.queue_drop(queue_id);
-        }
+        device.release(self.instance);
         Ok(())
     }
 }
@@ -588,6 +645,7 @@ impl<T: Send + WasiWebGpuCtxView> webgpu::HostGpuDeviceWithStore<T> for crate::H
             let device = ctx.table.get(&device)?;
             let device_id = device.device;
             let error_handler = Arc::clone(&device.error_handler);
+            let label = descriptor.label.clone();
 
             let (compute_pipeline_id, err) = ctx.instance.device_create_compute_pipeline(
                 device_id,
@@ -599,6 +657,7 @@ impl<T: Send + WasiWebGpuCtxView> webgpu::HostGpuDeviceWithStore<T> for crate::H
 
             Ok(Ok(ctx.table.push(ComputePipeline {
                 compute_pipeline_id,
+                label: label.unwrap_or_default(),
                 error_handler,
             })?))
         })
@@ -615,6 +674,7 @@ impl<T: Send + WasiWebGpuCtxView> webgpu::HostGpuDeviceWithStore<T> for crate::H
             let device = ctx.table.get(&device)?;
             let device_id = device.device;
             let error_handler = Arc::clone(&device.error_handler);
+            let label = descriptor.label.clone();
 
             let (render_pipeline_id, err) = ctx.instance.device_create_render_pipeline(
                 device_id,
@@ -626,6 +686,7 @@ impl<T: Send + WasiWebGpuCtxView> webgpu::HostGpuDeviceWithStore<T> for crate::H
 
             let render_pipeline = ctx.table.push(RenderPipeline {
                 render_pipeline_id,
+                label: label.unwrap_or_default(),
                 error_handler,
             })?;
             Ok(Ok(render_pipeline))
@@ -667,10 +728,18 @@ impl<T: Send + WasiWebGpuCtxView> webgpu::HostGpuDeviceWithStore<T> for crate::H
     }
 
     fn lost(
-        _accessor: Access<T, Self>,
-        _device: Resource<webgpu::GpuDevice>,
+        mut access: Access<T, Self>,
+        device: Resource<webgpu::GpuDevice>,
     ) -> wasmtime::Result<FutureReader<Resource<webgpu::GpuDeviceLostInfo>>> {
-        todo!()
+        let ctx = access.get();
+        let lost = ctx.table.get(&device)?.lost();
+        Ok(FutureReader::new(
+            access,
+            FuturePipeMap(lost, |data: &mut T, info| {
+                Ok(data.webgpu_ctx().table.push(info)?)
+            }),
+        )
+        .unwrap())
     }
 }
 
@@ -679,10 +748,11 @@ impl<'a> webgpu::HostGpuTexture for WasiWebGpuCtx<'a> {
         &mut self,
         texture: Resource<Texture>,
         descriptor: Option<webgpu::GpuTextureViewDescriptor>,
-    ) -> wasmtime::Result<Resource<wgpu_core::id::TextureViewId>> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuTextureView>> {
         let texture = self.table.get(&texture)?;
         let texture_id = texture.texture_id;
         let error_handler = Arc::clone(&texture.error_handler);
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
         let (texture_view, err) = self.instance.texture_create_view(
             texture_id,
             &descriptor
@@ -691,7 +761,7 @@ impl<'a> webgpu::HostGpuTexture for WasiWebGpuCtx<'a> {
             None,
         );
         error_handler.handle_possible_error(err);
-        Ok(self.table.push(texture_view)?)
+        Ok(self.table.push(TextureView::new(texture_view, label))?)
     }
 
     fn destroy(&mut self, texture: Resource<webgpu::GpuTexture>) -> wasmtime::Result<()> {
@@ -702,169 +772,186 @@ impl<'a> webgpu::HostGpuTexture for WasiWebGpuCtx<'a> {
 
     fn width(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuIntegerCoordinateOut> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.size.width)
     }
 
     fn height(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuIntegerCoordinateOut> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.size.height)
     }
 
     fn depth_or_array_layers(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuIntegerCoordinateOut> {
-        todo!()
+        Ok(self
+            .table
+            .get(&texture)?
+            .descriptor
+            .size
+            .depth_or_array_layers)
     }
 
     fn mip_level_count(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuIntegerCoordinateOut> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.mip_level_count)
     }
 
     fn sample_count(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuSize32Out> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.sample_count)
     }
 
     fn dimension(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuTextureDimension> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.dimension.into())
     }
 
     fn format(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuTextureFormat> {
-        todo!()
+        Ok(self.table.get(&texture)?.descriptor.format.into())
     }
 
     fn usage(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<webgpu::GpuTextureUsage> {
-        todo!()
+        self.table.get(&texture)?.descriptor.usage.try_into()
     }
 
     fn texture_binding_view_dimension(
         &mut self,
-        _texture: Resource<webgpu::GpuTexture>,
+        texture: Resource<webgpu::GpuTexture>,
     ) -> wasmtime::Result<Option<webgpu::GpuTextureViewDimension>> {
-        todo!()
+        Ok(self.table.get(&texture)?.texture_binding_view_dimension)
     }
 
-    fn label(&mut self, _self_: Resource<webgpu::GpuTexture>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, texture: Resource<webgpu::GpuTexture>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&texture)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuTexture>,
-        _label: String,
+        texture: Resource<webgpu::GpuTexture>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&texture)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, texture: Resource<webgpu::GpuTexture>) -> wasmtime::Result<()> {
         let texture = self.table.delete(texture)?;
-        self.instance.texture_drop(texture.texture_id);
+        // shared ids are dropped once the canvas context is done with them as well.
+        if texture.shared_id.is_none() {
+            self.instance.texture_drop(texture.texture_id);
+        }
         Ok(())
     }
 }
 
 impl<'a> webgpu::HostGpuTextureView for WasiWebGpuCtx<'a> {
-    fn label(
-        &mut self,
-        _self_: Resource<wgpu_core::id::TextureViewId>,
-    ) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, view: Resource<webgpu::GpuTextureView>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&view)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<wgpu_core::id::TextureViewId>,
-        _label: String,
+        view: Resource<webgpu::GpuTextureView>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&view)?.label = label;
+        Ok(())
     }
 
-    fn drop(&mut self, view: Resource<wgpu_core::id::TextureViewId>) -> wasmtime::Result<()> {
-        let view_id = self.table.delete(view)?;
-        self.instance.texture_view_drop(view_id);
+    fn drop(&mut self, view: Resource<webgpu::GpuTextureView>) -> wasmtime::Result<()> {
+        let view = self.table.delete(view)?;
+        self.instance.texture_view_drop(view.id);
         Ok(())
     }
 }
 
 impl<'a> webgpu::HostGpuCommandBuffer for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuCommandBuffer>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(
+        &mut self,
+        command_buffer: Resource<webgpu::GpuCommandBuffer>,
+    ) -> wasmtime::Result<String> {
+        Ok(self.table.get(&command_buffer)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuCommandBuffer>,
-        _label: String,
+        command_buffer: Resource<webgpu::GpuCommandBuffer>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&command_buffer)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, command_buffer: Resource<webgpu::GpuCommandBuffer>) -> wasmtime::Result<()> {
-        let command_buffer_id = self.table.delete(command_buffer)?;
-        self.instance.command_buffer_drop(command_buffer_id);
+        let command_buffer = self.table.delete(command_buffer)?;
+        self.instance.command_buffer_drop(command_buffer.id);
         Ok(())
     }
 }
 
 impl<'a> webgpu::HostGpuShaderModule for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuShaderModule>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, shader: Resource<webgpu::GpuShaderModule>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&shader)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuShaderModule>,
-        _label: String,
+        shader: Resource<webgpu::GpuShaderModule>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&shader)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, shader: Resource<webgpu::GpuShaderModule>) -> wasmtime::Result<()> {
-        let shader_id = self.table.delete(shader)?;
-        self.instance.shader_module_drop(shader_id);
+        let shader = self.table.delete(shader)?;
+        self.instance.shader_module_drop(shader.id);
         Ok(())
     }
 }
 
 impl<T: Send> webgpu::HostGpuShaderModuleWithStore<T> for crate::HasWasiWebGpuCtx {
     async fn get_compilation_info(
-        _accessor: &Accessor<T, Self>,
-        _shader: Resource<webgpu::GpuShaderModule>,
+        accessor: &Accessor<T, Self>,
+        shader: Resource<webgpu::GpuShaderModule>,
     ) -> wasmtime::Result<Resource<webgpu::GpuCompilationInfo>> {
-        todo!()
+        accessor.with(|mut access| {
+            let ctx = access.get();
+            let compilation_info = ctx.table.get(&shader)?.compilation_info.clone();
+            Ok(ctx.table.push(compilation_info)?)
+        })
     }
 }
 
 impl<'a> webgpu::HostGpuRenderPipeline for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<RenderPipeline>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, pipeline: Resource<RenderPipeline>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&pipeline)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<RenderPipeline>,
-        _label: String,
+        pipeline: Resource<RenderPipeline>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&pipeline)?.label = label;
+        Ok(())
     }
 
     fn get_bind_group_layout(
@@ -879,7 +966,7 @@ impl<'a> webgpu::HostGpuRenderPipeline for WasiWebGpuCtx<'a> {
             self.instance
                 .render_pipeline_get_bind_group_layout(pipeline_id, index, None);
         error_handler.handle_possible_error(err);
-        Ok(self.table.push(layout)?)
+        Ok(self.table.push(BindGroupLayout::new(layout, None))?)
     }
 
     fn drop(&mut self, pipeline: Resource<webgpu::GpuRenderPipeline>) -> wasmtime::Result<()> {
@@ -896,7 +983,7 @@ impl<'a> webgpu::HostGpuAdapter for WasiWebGpuCtx<'a> {
         adapter: Resource<webgpu::GpuAdapter>,
     ) -> wasmtime::Result<Resource<webgpu::GpuSupportedFeatures>> {
         let adapter = *(*self.table.get(&adapter)?);
-        let features = self.instance.adapter_features(adapter);
+        let features = guest_features(self.instance, self.instance.adapter_features(adapter));
         Ok(self.table.push(features)?)
     }
 
@@ -937,24 +1024,48 @@ impl<T: Send> webgpu::HostGpuAdapterWithStore<T> for crate::HasWasiWebGpuCtx {
             let ctx = access.get();
 
             let adapter = Arc::clone(ctx.table.get(&adapter)?);
+            let label = descriptor.as_ref().and_then(|d| d.label.clone());
+            let queue_label = descriptor
+                .as_ref()
+                .and_then(|d| d.default_queue.as_ref())
+                .and_then(|q| q.label.clone());
+
+            let mut descriptor = descriptor
+                .map(|d| d.to_core(ctx.table))
+                .unwrap_or(wgpu_types::DeviceDescriptor::default());
+            if let Err(e) =
+                enable_immediates(&mut descriptor, ctx.instance.adapter_features(*adapter))
+            {
+                return Ok(Err(e));
+            }
+            let hidden_features = descriptor.required_features
+                - guest_features(ctx.instance, descriptor.required_features);
+            if !hidden_features.is_empty() {
+                // From the spec:
+                // > 1. If any of the following requirements are unmet:
+                // >  - The set of values in descriptor.requiredFeatures must be a subset of those in adapter.[[features]].
+                // > Then issue the following steps on contentTimeline and return:
+                // >  1. Reject promise with a TypeError.
+                return Ok(Err(webgpu::RequestDeviceError {
+                    kind: webgpu::RequestDeviceErrorKind::TypeError,
+                    message: format!("unsupported features: {hidden_features:?}"),
+                }));
+            }
 
-            let device_queue_result = ctx.instance.adapter_request_device(
-                *adapter,
-                &descriptor
-                    .map(|d| d.to_core(ctx.table))
-                    .unwrap_or(wgpu_types::DeviceDescriptor::default()),
-                None,
-                None,
-            );
+            let device_queue_result =
+                ctx.instance
+                    .adapter_request_device(*adapter, &descriptor, None, None);
 
             Ok(match device_queue_result {
                 Ok((device_id, queue_id)) => {
-                    let device = ctx.table.push(Device {
-                        device: device_id,
-                        queue: Arc::new(queue_id),
+                    let device = ctx.table.push(Device::new(
+                        ctx.instance,
+                        device_id,
+                        queue_id,
                         adapter,
-                        error_handler: Arc::new(ErrorHandler::default()),
-                    })?;
+                        label,
+                        queue_label,
+                    ))?;
                     Ok(device)
                 }
 
@@ -1003,9 +1114,9 @@ impl<'a> webgpu::HostGpuQueue for WasiWebGpuCtx<'a> {
     ) -> wasmtime::Result<()> {
         let command_buffers = val
             .into_iter()
-            .map(|buffer| *self.table.get(&buffer).unwrap())
+            .map(|buffer| self.table.get(&buffer).unwrap().id)
             .collect::<Vec<_>>();
-        let queue = *(*self.table.get(&queue)?);
+        let queue = *self.table.get(&queue)?.id;
         self.instance.queue_submit(queue, &command_buffers).unwrap();
         Ok(())
     }
@@ -1019,19 +1130,59 @@ impl<'a> webgpu::HostGpuQueue for WasiWebGpuCtx<'a> {
         data_offset: Option<webgpu::GpuSize64>,
         size: Option<webgpu::GpuSize64>,
     ) -> wasmtime::Result<Result<(), webgpu::WriteBufferError>> {
-        let queue = *(*self.table.get(&queue)?);
+        let queue = self.table.get(&queue)?;
+        let queue_id = *queue.id;
+        let error_handler = Arc::clone(&queue.error_handler);
         let buffer_id = self.table.get(&buffer)?.buffer_id;
-        let mut data = &data[..];
-        if let Some(data_offset) = data_offset {
-            let data_offset = data_offset as usize;
-            data = &data[data_offset..];
-        }
-        if let Some(size) = size {
-            let size = size as usize;
-            data = &data[..size];
+
+        // https://www.w3.org/TR/webgpu/#dom-gpuqueue-writebuffer
+        // > 4. If dataOffset is greater than dataSize, throw OperationError and return.
+        // > 5. Let contentsSize be dataSize − dataOffset.
+        // > 6. If size is given: if contentsSize < size, throw OperationError and return.
+        // > 8. If contentsSize is not a multiple of 4 bytes, throw OperationError and return.
+        let data_offset = data_offset.unwrap_or(0);
+        let Some(contents) = usize::try_from(data_offset)
+            .ok()
+            .and_then(|data_offset| data.get(data_offset..))
+        else {
+            return Ok(Err(webgpu::WriteBufferError {
+                kind: webgpu::WriteBufferErrorKind::OperationError,
+                message: format!(
+                    "data offset {data_offset} is out of bounds for data of size {}",
+                    data.len()
+                ),
+            }));
+        };
+        let contents = match size {
+            Some(size) => match usize::try_from(size)
+                .ok()
+                .and_then(|size| contents.get(..size))
+            {
+                Some(contents) => contents,
+                None => {
+                    return Ok(Err(webgpu::WriteBufferError {
+                        kind: webgpu::WriteBufferErrorKind::OperationError,
+                        message: format!(
+                            "size {size} is larger than the {} bytes of data after the data offset",
+                            contents.len()
+                        ),
+                    }));
+                }
+            },
+            None => contents,
+        };
+        if !contents.len().is_multiple_of(4) {
+            return Ok(Err(webgpu::WriteBufferError {
+                kind: webgpu::WriteBufferErrorKind::OperationError,
+                message: format!("write size {} is not a multiple of 4 bytes", contents.len()),
+            }));
         }
-        self.instance
-            .queue_write_buffer(queue, buffer_id, buffer_offset, data)?;
+
+        let err = self
+            .instance
+            .queue_write_buffer(queue_id, buffer_id, buffer_offset, contents)
+            .err();
+        error_handler.handle_possible_error(err);
         Ok(Ok(()))
     }
 
@@ -1043,7 +1194,7 @@ impl<'a> webgpu::HostGpuQueue for WasiWebGpuCtx<'a> {
         data_layout: webgpu::GpuTexelCopyBufferLayout,
         size: webgpu::GpuExtent3D,
     ) -> wasmtime::Result<()> {
-        let queue = *(*self.table.get(&queue)?);
+        let queue = *self.table.get(&queue)?.id;
         self.instance.queue_write_texture(
             queue,
             &destination.to_core(self.table),
@@ -1054,21 +1205,22 @@ impl<'a> webgpu::HostGpuQueue for WasiWebGpuCtx<'a> {
         Ok(())
     }
 
-    fn label(&mut self, _self_: Resource<webgpu::GpuQueue>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, queue: Resource<webgpu::GpuQueue>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&queue)?.label.lock().unwrap().clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuQueue>,
-        _label: String,
+        queue: Resource<webgpu::GpuQueue>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        *self.table.get(&queue)?.label.lock().unwrap() = label;
+        Ok(())
     }
 
     fn drop(&mut self, queue: Resource<webgpu::GpuQueue>) -> wasmtime::Result<()> {
-        let queue_id = self.table.delete(queue)?;
-        if let Some(queue_id) = Arc::into_inner(queue_id) {
+        let queue = self.table.delete(queue)?;
+        if let Some(queue_id) = Arc::into_inner(queue.id) {
             self.instance.queue_drop(queue_id);
         }
         Ok(())
@@ -1080,21 +1232,34 @@ impl<T: Send> webgpu::HostGpuQueueWithStore<T> for crate::HasWasiWebGpuCtx {
         accessor: &Accessor<T, Self>,
         queue: Resource<webgpu::GpuQueue>,
     ) -> wasmtime::Result<()> {
-        accessor.with(|mut access| -> wasmtime::Result<_> {
+        let (instance, queue_id, poller) = accessor.with(|mut access| -> wasmtime::Result<_> {
             let ctx = access.get();
-            let instance = Arc::clone(ctx.instance);
-            let queue_id = **ctx.table.get(&queue)?;
-
-            CallbackFuture::new(Box::new(move |resolve: Box<dyn FnOnce(()) + Send>| {
-                instance.queue_on_submitted_work_done(queue_id, Box::new(move || resolve(())));
-            }));
-            Ok(())
+            let queue = ctx.table.get(&queue)?;
+            Ok((
+                Arc::clone(ctx.instance),
+                *queue.id,
+                Arc::clone(&queue.poller),
+            ))
         })?;
 
+        submitted_work_done(instance, queue_id, poller).await;
         Ok(())
     }
 }
 
+// Resolves once the GPU is done with everything submitted to the queue so far.
+// The callback is called from the device's poller thread, so waiting doesn't block the async runtime.
+fn submitted_work_done(
+    instance: Arc<wgpu_core::global::Global>,
+    queue_id: wgpu_core::id::QueueId,
+    poller: Arc<crate::types::DevicePoller>,
+) -> CallbackFuture<()> {
+    CallbackFuture::new(Box::new(move |resolve: Box<dyn FnOnce(()) + Send>| {
+        instance.queue_on_submitted_work_done(queue_id, Box::new(move || resolve(())));
+        poller.poll();
+    }))
+}
+
 impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
     fn begin_render_pass(
         &mut self,
@@ -1104,6 +1269,14 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
         let command_encoder = self.table.get(&command_encoder)?;
         let command_encoder_id = command_encoder.command_encoder_id;
         let error_handler = Arc::clone(&command_encoder.error_handler);
+        let label = descriptor.label.clone();
+        for query_set in descriptor
+            .occlusion_query_set
+            .iter()
+            .chain(descriptor.timestamp_writes.as_ref().map(|tw| &tw.query_set))
+        {
+            error_handler.handle_possible_error(self.table.get(query_set)?.validate());
+        }
         let timestamp_writes = descriptor.timestamp_writes.map(|tw| tw.to_core(self.table));
         // can't use to_core because depth_stencil_attachment is Option<&x>.
         let depth_stencil_attachment = descriptor
@@ -1132,7 +1305,9 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
 
         error_handler.handle_possible_error(err);
 
-        Ok(self.table.push(RenderPassEncoder::new(render_pass))?)
+        Ok(self
+            .table
+            .push(RenderPassEncoder::new(render_pass, label, error_handler))?)
     }
 
     fn finish(
@@ -1143,6 +1318,7 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
         let command_encoder = self.table.get(&command_encoder)?;
         let command_encoder_id = command_encoder.command_encoder_id;
         let error_handler = Arc::clone(&command_encoder.error_handler);
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
         let (command_buffer, err) = self.instance.command_encoder_finish(
             command_encoder_id,
             &descriptor
@@ -1150,11 +1326,9 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
                 .unwrap_or(wgpu_types::CommandBufferDescriptor::default()),
             None,
         );
-        // dropping the label.
-        // TODO: reconsider when implementing real labels.
-        let err = err.map(|(_label, err)| err);
+        let err = err.map(|(label, error)| LabeledError { label, error });
         error_handler.handle_possible_error(err);
-        Ok(self.table.push(command_buffer)?)
+        Ok(self.table.push(CommandBuffer::new(command_buffer, label))?)
     }
 
     fn begin_compute_pass(
@@ -1165,6 +1339,14 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
         let command_encoder = self.table.get(&command_encoder)?;
         let command_encoder_id = command_encoder.command_encoder_id;
         let error_handler = Arc::clone(&command_encoder.error_handler);
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
+        if let Some(timestamp_writes) = descriptor
+            .as_ref()
+            .and_then(|d| d.timestamp_writes.as_ref())
+        {
+            error_handler
+                .handle_possible_error(self.table.get(&timestamp_writes.query_set)?.validate());
+        }
         let (compute_pass, err) = self.instance.command_encoder_begin_compute_pass(
             command_encoder_id,
             // can't use to_core because timestamp_writes is Option<&x>.
@@ -1178,7 +1360,9 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
             },
         );
         error_handler.handle_possible_error(err);
-        Ok(self.table.push(ComputePassEncoder::new(compute_pass))?)
+        Ok(self
+            .table
+            .push(ComputePassEncoder::new(compute_pass, label, error_handler))?)
     }
 
     fn copy_buffer_to_buffer(
@@ -1285,9 +1469,15 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
         destination: Resource<webgpu::GpuBuffer>,
         destination_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
-        let query_set_id = *self.table.get(&query_set)?;
+        let query_set = self.table.get(&query_set)?;
+        let query_set_id = query_set.id;
+        let destroyed_error = query_set.validate();
         let destination = self.table.get(&destination)?.buffer_id;
-        let command_encoder = self.table.get(&command_encoder)?.command_encoder_id;
+        let command_encoder = self.table.get(&command_encoder)?;
+        command_encoder
+            .error_handler
+            .handle_possible_error(destroyed_error);
+        let command_encoder = command_encoder.command_encoder_id;
         self.instance.command_encoder_resolve_query_set(
             command_encoder,
             query_set_id,
@@ -1300,17 +1490,16 @@ impl<'a> webgpu::HostGpuCommandEncoder for WasiWebGpuCtx<'a> {
     }
 
     fn label(&mut self, command_encoder: Resource<CommandEncoder>) -> wasmtime::Result<String> {
-        let _command_encoder = self.table.get(&command_encoder)?;
-        // TODO: return real label
-        Ok(String::new())
+        Ok(self.table.get(&command_encoder)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<CommandEncoder>,
-        _label: String,
+        command_encoder: Resource<CommandEncoder>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&command_encoder)?.label = label;
+        Ok(())
     }
 
     fn push_debug_group(
@@ -1360,10 +1549,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         pipeline: Resource<webgpu::GpuRenderPipeline>,
     ) -> wasmtime::Result<()> {
         let pipeline_id = self.table.get(&pipeline)?.render_pipeline_id;
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_set_pipeline(render_pass, pipeline_id)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_set_pipeline(render_pass, pipeline_id)
+        });
         Ok(())
     }
 
@@ -1375,23 +1565,27 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         first_vertex: Option<webgpu::GpuSize32>,
         first_instance: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-        self.instance.render_pass_draw(
-            render_pass,
-            vertex_count,
-            instance_count.unwrap_or(1),
-            first_vertex.unwrap_or(0),
-            first_instance.unwrap_or(0),
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+            self.instance.render_pass_draw(
+                render_pass,
+                vertex_count,
+                instance_count.unwrap_or(1),
+                first_vertex.unwrap_or(0),
+                first_instance.unwrap_or(0),
+            )
+        });
         Ok(())
     }
 
     fn end(&mut self, render_pass: Resource<RenderPassEncoder>) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let mut render_pass = render_pass.take().unwrap();
-        self.instance.render_pass_end(&mut render_pass)?;
+        let render_pass = self.table.get(&render_pass)?;
+        let err = match render_pass.render_pass.take() {
+            Some(mut pass) => self.instance.render_pass_end(&mut pass).err(),
+            None => Some(wgpu_core::command::EncoderStateError::Ended),
+        };
+        render_pass.error_handler.handle_possible_error(err);
         Ok(())
     }
 
@@ -1405,17 +1599,18 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         min_depth: f32,
         max_depth: f32,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_set_viewport(
-            render_pass,
-            x,
-            y,
-            width,
-            height,
-            min_depth,
-            max_depth,
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_set_viewport(
+                render_pass,
+                x,
+                y,
+                width,
+                height,
+                min_depth,
+                max_depth,
+            )
+        });
         Ok(())
     }
 
@@ -1427,10 +1622,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         width: webgpu::GpuIntegerCoordinate,
         height: webgpu::GpuIntegerCoordinate,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_set_scissor_rect(render_pass, x, y, width, height)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_set_scissor_rect(render_pass, x, y, width, height)
+        });
         Ok(())
     }
 
@@ -1439,10 +1635,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         color: webgpu::GpuColor,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_set_blend_constant(render_pass, color.into())?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_set_blend_constant(render_pass, color.into())
+        });
         Ok(())
     }
 
@@ -1451,10 +1648,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         reference: webgpu::GpuStencilValue,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_set_stencil_reference(render_pass, reference)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_set_stencil_reference(render_pass, reference)
+        });
         Ok(())
     }
 
@@ -1463,10 +1661,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         query_index: webgpu::GpuSize32,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_begin_occlusion_query(render_pass, query_index)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_begin_occlusion_query(render_pass, query_index)
+        });
         Ok(())
     }
 
@@ -1474,9 +1673,9 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         &mut self,
         render_pass: Resource<RenderPassEncoder>,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_end_occlusion_query(render_pass)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass
+            .encode(|render_pass| self.instance.render_pass_end_occlusion_query(render_pass));
         Ok(())
     }
 
@@ -1485,27 +1684,40 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         bundles: Vec<Resource<webgpu::GpuRenderBundle>>,
     ) -> wasmtime::Result<()> {
-        let render_bundle_ids = bundles
+        let render_pass = self.table.get(&render_pass)?;
+        let render_bundle_ids = match bundles
             .iter()
-            .map(|bundle| *self.table.get(bundle).unwrap())
-            .collect::<Vec<_>>();
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_execute_bundles(render_pass, &render_bundle_ids)?;
+            .map(|bundle| self.table.get(bundle).map(|bundle| bundle.id))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(ids) => ids,
+            Err(e) => {
+                render_pass
+                    .error_handler
+                    .handle_possible_error(Some(ValidationError(format!(
+                        "Invalid render bundle: {e}"
+                    ))));
+                return Ok(());
+            }
+        };
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_execute_bundles(render_pass, &render_bundle_ids)
+        });
         Ok(())
     }
 
-    fn label(&mut self, _self_: Resource<RenderPassEncoder>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, render_pass: Resource<RenderPassEncoder>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&render_pass)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<RenderPassEncoder>,
-        _label: String,
+        render_pass: Resource<RenderPassEncoder>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&render_pass)?.label = label;
+        Ok(())
     }
 
     fn push_debug_group(
@@ -1513,10 +1725,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         group_label: String,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_push_debug_group(render_pass, &group_label, 0)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_push_debug_group(render_pass, &group_label, 0)
+        });
         Ok(())
     }
 
@@ -1524,9 +1737,8 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         &mut self,
         render_pass: Resource<RenderPassEncoder>,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_pop_debug_group(render_pass)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| self.instance.render_pass_pop_debug_group(render_pass));
         Ok(())
     }
 
@@ -1535,10 +1747,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         render_pass: Resource<RenderPassEncoder>,
         marker_label: String,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_insert_debug_marker(render_pass, &marker_label, 0)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_insert_debug_marker(render_pass, &marker_label, 0)
+        });
         Ok(())
     }
 
@@ -1551,27 +1764,28 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         dynamic_offsets_data_start: Option<webgpu::GpuSize64>,
         dynamic_offsets_data_length: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<Result<(), webgpu::SetBindGroupError>> {
-        let bind_group = bind_group.map(|bind_group| *self.table.get(&bind_group).unwrap());
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#programmable-passes
-        let dynamic_offsets = &dynamic_offsets_data.unwrap_or(vec![]);
-        let mut dynamic_offsets = &dynamic_offsets[..];
-        if let Some(dynamic_offsets_data_start) = dynamic_offsets_data_start {
-            let dynamic_offsets_data_start = dynamic_offsets_data_start as usize;
-            dynamic_offsets = &dynamic_offsets[dynamic_offsets_data_start..];
-        }
-        if let Some(dynamic_offsets_data_length) = dynamic_offsets_data_length {
-            let dynamic_offsets_data_length = dynamic_offsets_data_length as usize;
-            dynamic_offsets = &dynamic_offsets[..dynamic_offsets_data_length];
-        }
-
-        self.instance.render_pass_set_bind_group(
-            render_pass,
-            index,
-            bind_group,
-            dynamic_offsets,
-        )?;
+        let dynamic_offsets_data = dynamic_offsets_data.unwrap_or_default();
+        let dynamic_offsets = match dynamic_offsets(
+            &dynamic_offsets_data,
+            dynamic_offsets_data_start,
+            dynamic_offsets_data_length,
+        ) {
+            Ok(dynamic_offsets) => dynamic_offsets,
+            Err(e) => return Ok(Err(e)),
+        };
+        let bind_group = match bind_group {
+            Some(bind_group) => Some(self.table.get(&bind_group)?.id),
+            None => None,
+        };
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_set_bind_group(
+                render_pass,
+                index,
+                bind_group,
+                dynamic_offsets,
+            )
+        });
         Ok(Ok(()))
     }
 
@@ -1584,16 +1798,24 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         size: Option<webgpu::GpuSize64>,
     ) -> wasmtime::Result<()> {
         let buffer_id = self.table.get(&buffer)?.buffer_id;
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_set_index_buffer(
-            render_pass,
-            buffer_id,
-            index_format.into(),
-            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-            offset.unwrap_or(0),
-            size.map(|s| NonZeroU64::new(s).expect("Size can't be zero")),
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        let size = match binding_size(size) {
+            Ok(size) => size,
+            Err(e) => {
+                render_pass.error_handler.handle_possible_error(Some(e));
+                return Ok(());
+            }
+        };
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_set_index_buffer(
+                render_pass,
+                buffer_id,
+                index_format.into(),
+                // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+                offset.unwrap_or(0),
+                size,
+            )
+        });
         Ok(())
     }
 
@@ -1609,16 +1831,24 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
             .table
             .get(&buffer.expect("TODO: deal null buffers"))?
             .buffer_id;
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_set_vertex_buffer(
-            render_pass,
-            slot,
-            buffer_id,
-            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-            offset.unwrap_or(0),
-            size.map(|s| NonZeroU64::new(s).expect("Size can't be zero")),
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        let size = match binding_size(size) {
+            Ok(size) => size,
+            Err(e) => {
+                render_pass.error_handler.handle_possible_error(Some(e));
+                return Ok(());
+            }
+        };
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_set_vertex_buffer(
+                render_pass,
+                slot,
+                buffer_id,
+                // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+                offset.unwrap_or(0),
+                size,
+            )
+        });
         Ok(())
     }
 
@@ -1631,17 +1861,18 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         base_vertex: Option<webgpu::GpuSignedOffset32>,
         first_instance: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<()> {
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_draw_indexed(
-            render_pass,
-            index_count,
-            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-            instance_count.unwrap_or(1),
-            first_index.unwrap_or(0),
-            base_vertex.unwrap_or(0),
-            first_instance.unwrap_or(0),
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_draw_indexed(
+                render_pass,
+                index_count,
+                // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+                instance_count.unwrap_or(1),
+                first_index.unwrap_or(0),
+                base_vertex.unwrap_or(0),
+                first_instance.unwrap_or(0),
+            )
+        });
         Ok(())
     }
 
@@ -1652,10 +1883,11 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         indirect_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
         let indirect_buffer = self.table.get(&indirect_buffer)?.buffer_id;
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance
-            .render_pass_draw_indirect(render_pass, indirect_buffer, indirect_offset)?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance
+                .render_pass_draw_indirect(render_pass, indirect_buffer, indirect_offset)
+        });
         Ok(())
     }
 
@@ -1666,25 +1898,38 @@ impl<'a> webgpu::HostGpuRenderPassEncoder for WasiWebGpuCtx<'a> {
         indirect_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
         let indirect_buffer = self.table.get(&indirect_buffer)?.buffer_id;
-        let mut render_pass = self.table.get_mut(&render_pass)?.lock();
-        let render_pass = render_pass.as_mut().unwrap();
-        self.instance.render_pass_draw_indexed_indirect(
-            render_pass,
-            indirect_buffer,
-            indirect_offset,
-        )?;
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|render_pass| {
+            self.instance.render_pass_draw_indexed_indirect(
+                render_pass,
+                indirect_buffer,
+                indirect_offset,
+            )
+        });
         Ok(())
     }
 
     fn set_immediates(
         &mut self,
-        _render_pass: Resource<webgpu::GpuRenderPassEncoder>,
-        _range_offset: u32,
-        _data: Vec<u8>,
-        _data_offset: Option<u64>,
-        _data_size: Option<u64>,
+        render_pass: Resource<webgpu::GpuRenderPassEncoder>,
+        range_offset: u32,
+        data: Vec<u8>,
+        data_offset: Option<u64>,
+        data_size: Option<u64>,
     ) -> wasmtime::Result<()> {
-        todo!()
+        let render_pass = self.table.get(&render_pass)?;
+        render_pass.encode(|pass| {
+            match immediates_data(&data, range_offset, data_offset, data_size) {
+                Ok(data) => self
+                    .instance
+                    .render_pass_set_immediates(pass, range_offset, data),
+                Err(err) => {
+                    render_pass.error_handler.handle_possible_error(Some(err));
+                    Ok(())
+                }
+            }
+        });
+        Ok(())
     }
 
     fn drop(&mut self, render_pass: Resource<RenderPassEncoder>) -> wasmtime::Result<()> {
@@ -1777,66 +2022,97 @@ impl<'a> webgpu::HostGpuError for WasiWebGpuCtx<'a> {
 impl<'a> webgpu::HostGpuDeviceLostInfo for WasiWebGpuCtx<'a> {
     fn reason(
         &mut self,
-        _self_: Resource<webgpu::GpuDeviceLostInfo>,
+        info: Resource<webgpu::GpuDeviceLostInfo>,
     ) -> wasmtime::Result<webgpu::GpuDeviceLostReason> {
-        todo!()
+        let info = self.table.get(&info)?;
+        Ok(info.reason)
     }
 
-    fn message(&mut self, _self_: Resource<webgpu::GpuDeviceLostInfo>) -> wasmtime::Result<String> {
-        todo!()
+    fn message(&mut self, info: Resource<webgpu::GpuDeviceLostInfo>) -> wasmtime::Result<String> {
+        let info = self.table.get(&info)?;
+        Ok(info.message.clone())
     }
 
-    fn drop(&mut self, _info: Resource<webgpu::GpuDeviceLostInfo>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, info: Resource<webgpu::GpuDeviceLostInfo>) -> wasmtime::Result<()> {
+        self.table.delete(info)?;
+        Ok(())
     }
 }
 impl<'a> webgpu::HostGpuCanvasContext for WasiWebGpuCtx<'a> {
     fn configure(
         &mut self,
-        _self_: Resource<webgpu::GpuCanvasContext>,
-        _configuration: webgpu::GpuCanvasConfiguration,
+        context: Resource<webgpu::GpuCanvasContext>,
+        configuration: webgpu::GpuCanvasConfiguration,
     ) -> wasmtime::Result<()> {
-        todo!()
+        let device = self.table.get(&configuration.device)?.clone();
+        let context = self.table.get_mut(&context)?;
+        context.configure(self.instance, device, configuration);
+        Ok(())
     }
 
     fn get_configuration(
         &mut self,
-        _self_: Resource<webgpu::GpuCanvasContext>,
+        context: Resource<webgpu::GpuCanvasContext>,
     ) -> wasmtime::Result<Option<webgpu::GpuCanvasConfigurationOwned>> {
-        todo!()
+        let context = self.table.get(&context)?;
+        let Some(configuration) = context.configuration() else {
+            return Ok(None);
+        };
+        let format = configuration.format;
+        let usage = configuration.usage;
+        let view_formats = configuration.view_formats.clone();
+        let color_space = configuration.color_space;
+        let tone_mapping = configuration.tone_mapping;
+        let alpha_mode = configuration.alpha_mode;
+        let device = configuration.device.clone();
+        let device = self.table.push(device)?;
+        Ok(Some(webgpu::GpuCanvasConfigurationOwned {
+            device,
+            format,
+            usage,
+            view_formats,
+            color_space,
+            tone_mapping,
+            alpha_mode,
+        }))
     }
 
-    fn unconfigure(&mut self, _self_: Resource<webgpu::GpuCanvasContext>) -> wasmtime::Result<()> {
-        todo!()
+    fn unconfigure(&mut self, context: Resource<webgpu::GpuCanvasContext>) -> wasmtime::Result<()> {
+        self.table.get_mut(&context)?.unconfigure(self.instance);
+        Ok(())
     }
 
     fn get_current_texture(
         &mut self,
-        _self_: Resource<webgpu::GpuCanvasContext>,
+        context: Resource<webgpu::GpuCanvasContext>,
     ) -> wasmtime::Result<Resource<webgpu::GpuTexture>> {
-        todo!()
+        let texture = self.table.get_mut(&context)?.get_current_texture()?;
+        Ok(self.table.push(texture)?)
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuCanvasContext>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, context: Resource<webgpu::GpuCanvasContext>) -> wasmtime::Result<()> {
+        let mut context = self.table.delete(context)?;
+        context.unconfigure(self.instance);
+        Ok(())
     }
 }
 impl<'a> webgpu::HostGpuRenderBundle for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuRenderBundle>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, bundle: Resource<webgpu::GpuRenderBundle>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&bundle)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuRenderBundle>,
-        _label: String,
+        bundle: Resource<webgpu::GpuRenderBundle>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&bundle)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, _bundle: Resource<webgpu::GpuRenderBundle>) -> wasmtime::Result<()> {
-        let bundle_id = self.table.delete(_bundle)?;
-        self.instance.render_bundle_drop(bundle_id);
+        let bundle = self.table.delete(_bundle)?;
+        self.instance.render_bundle_drop(bundle.id);
         Ok(())
     }
 }
@@ -1847,10 +2123,11 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         pipeline: Resource<webgpu::GpuComputePipeline>,
     ) -> wasmtime::Result<()> {
         let pipeline = self.table.get(&pipeline)?.compute_pipeline_id;
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        self.instance
-            .compute_pass_set_pipeline(compute_pass, pipeline)?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            self.instance
+                .compute_pass_set_pipeline(compute_pass, pipeline)
+        });
         Ok(())
     }
 
@@ -1861,15 +2138,16 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         workgroup_count_y: Option<webgpu::GpuSize32>,
         workgroup_count_z: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<()> {
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpucomputepassencoder
-        self.instance.compute_pass_dispatch_workgroups(
-            compute_pass,
-            workgroup_count_x,
-            workgroup_count_y.unwrap_or(1),
-            workgroup_count_z.unwrap_or(1),
-        )?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            // https://www.w3.org/TR/webgpu/#gpucomputepassencoder
+            self.instance.compute_pass_dispatch_workgroups(
+                compute_pass,
+                workgroup_count_x,
+                workgroup_count_y.unwrap_or(1),
+                workgroup_count_z.unwrap_or(1),
+            )
+        });
         Ok(())
     }
 
@@ -1880,13 +2158,14 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         indirect_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
         let indirect_buffer = self.table.get(&indirect_buffer)?.buffer_id;
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        self.instance.compute_pass_dispatch_workgroups_indirect(
-            compute_pass,
-            indirect_buffer,
-            indirect_offset,
-        )?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            self.instance.compute_pass_dispatch_workgroups_indirect(
+                compute_pass,
+                indirect_buffer,
+                indirect_offset,
+            )
+        });
         Ok(())
     }
 
@@ -1894,25 +2173,29 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         &mut self,
         compute_pass: Resource<webgpu::GpuComputePassEncoder>,
     ) -> wasmtime::Result<()> {
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let mut compute_pass = compute_pass.take().unwrap();
-        self.instance.compute_pass_end(&mut compute_pass)?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        let err = match compute_pass.compute_pass.take() {
+            Some(mut pass) => self.instance.compute_pass_end(&mut pass).err(),
+            None => Some(wgpu_core::command::EncoderStateError::Ended),
+        };
+        compute_pass.error_handler.handle_possible_error(err);
         Ok(())
     }
 
     fn label(
         &mut self,
-        _self_: Resource<webgpu::GpuComputePassEncoder>,
+        compute_pass: Resource<webgpu::GpuComputePassEncoder>,
     ) -> wasmtime::Result<String> {
-        todo!()
+        Ok(self.table.get(&compute_pass)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuComputePassEncoder>,
-        _label: String,
+        compute_pass: Resource<webgpu::GpuComputePassEncoder>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&compute_pass)?.label = label;
+        Ok(())
     }
 
     fn push_debug_group(
@@ -1920,10 +2203,11 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         compute_pass: Resource<webgpu::GpuComputePassEncoder>,
         group_label: String,
     ) -> wasmtime::Result<()> {
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        self.instance
-            .compute_pass_push_debug_group(compute_pass, &group_label, 0)?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            self.instance
+                .compute_pass_push_debug_group(compute_pass, &group_label, 0)
+        });
         Ok(())
     }
 
@@ -1931,9 +2215,9 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         &mut self,
         compute_pass: Resource<webgpu::GpuComputePassEncoder>,
     ) -> wasmtime::Result<()> {
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        self.instance.compute_pass_pop_debug_group(compute_pass)?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass
+            .encode(|compute_pass| self.instance.compute_pass_pop_debug_group(compute_pass));
         Ok(())
     }
 
@@ -1942,10 +2226,11 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         compute_pass: Resource<webgpu::GpuComputePassEncoder>,
         label: String,
     ) -> wasmtime::Result<()> {
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        self.instance
-            .compute_pass_insert_debug_marker(compute_pass, &label, 0)?;
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            self.instance
+                .compute_pass_insert_debug_marker(compute_pass, &label, 0)
+        });
         Ok(())
     }
 
@@ -1958,40 +2243,52 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
         dynamic_offsets_data_start: Option<webgpu::GpuSize64>,
         dynamic_offsets_data_length: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<Result<(), webgpu::SetBindGroupError>> {
-        let bind_group = bind_group.map(|bind_group| *self.table.get(&bind_group).unwrap());
-        let mut compute_pass = self.table.get_mut(&compute_pass)?.lock();
-        let compute_pass = compute_pass.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#programmable-passes
-
-        let dynamic_offsets = &dynamic_offsets_data.unwrap_or(vec![]);
-        let mut dynamic_offsets = &dynamic_offsets[..];
-        if let Some(dynamic_offsets_data_start) = dynamic_offsets_data_start {
-            let dynamic_offsets_data_start = dynamic_offsets_data_start as usize;
-            dynamic_offsets = &dynamic_offsets[dynamic_offsets_data_start..];
-        }
-        if let Some(dynamic_offsets_data_length) = dynamic_offsets_data_length {
-            let dynamic_offsets_data_length = dynamic_offsets_data_length as usize;
-            dynamic_offsets = &dynamic_offsets[..dynamic_offsets_data_length];
-        }
-
-        self.instance.compute_pass_set_bind_group(
-            compute_pass,
-            index,
-            bind_group,
-            dynamic_offsets,
-        )?;
+        let dynamic_offsets_data = dynamic_offsets_data.unwrap_or_default();
+        let dynamic_offsets = match dynamic_offsets(
+            &dynamic_offsets_data,
+            dynamic_offsets_data_start,
+            dynamic_offsets_data_length,
+        ) {
+            Ok(dynamic_offsets) => dynamic_offsets,
+            Err(e) => return Ok(Err(e)),
+        };
+        let bind_group = match bind_group {
+            Some(bind_group) => Some(self.table.get(&bind_group)?.id),
+            None => None,
+        };
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|compute_pass| {
+            self.instance.compute_pass_set_bind_group(
+                compute_pass,
+                index,
+                bind_group,
+                dynamic_offsets,
+            )
+        });
         Ok(Ok(()))
     }
 
     fn set_immediates(
         &mut self,
-        _render_pass: Resource<webgpu::GpuComputePassEncoder>,
-        _range_offset: u32,
-        _data: Vec<u8>,
-        _data_offset: Option<u64>,
-        _data_size: Option<u64>,
+        compute_pass: Resource<webgpu::GpuComputePassEncoder>,
+        range_offset: u32,
+        data: Vec<u8>,
+        data_offset: Option<u64>,
+        data_size: Option<u64>,
     ) -> wasmtime::Result<()> {
-        todo!()
+        let compute_pass = self.table.get(&compute_pass)?;
+        compute_pass.encode(|pass| {
+            match immediates_data(&data, range_offset, data_offset, data_size) {
+                Ok(data) => self
+                    .instance
+                    .compute_pass_set_immediates(pass, range_offset, data),
+                Err(err) => {
+                    compute_pass.error_handler.handle_possible_error(Some(err));
+                    Ok(())
+                }
+            }
+        });
+        Ok(())
     }
 
     fn drop(
@@ -2026,91 +2323,104 @@ impl<'a> webgpu::HostGpuComputePassEncoder for WasiWebGpuCtx<'a> {
 impl<'a> webgpu::HostGpuCompilationMessage for WasiWebGpuCtx<'a> {
     fn message(
         &mut self,
-        _self_: Resource<webgpu::GpuCompilationMessage>,
+        message: Resource<webgpu::GpuCompilationMessage>,
     ) -> wasmtime::Result<String> {
-        todo!()
+        Ok(self.table.get(&message)?.message.clone())
     }
 
     fn type_(
         &mut self,
-        _self_: Resource<webgpu::GpuCompilationMessage>,
+        message: Resource<webgpu::GpuCompilationMessage>,
     ) -> wasmtime::Result<webgpu::GpuCompilationMessageType> {
-        todo!()
+        Ok(self.table.get(&message)?.message_type)
     }
 
     fn line_num(
         &mut self,
-        _self_: Resource<webgpu::GpuCompilationMessage>,
+        message: Resource<webgpu::GpuCompilationMessage>,
     ) -> wasmtime::Result<u64> {
-        todo!()
+        Ok(self.table.get(&message)?.line_num)
     }
 
     fn line_pos(
         &mut self,
-        _self_: Resource<webgpu::GpuCompilationMessage>,
+        message: Resource<webgpu::GpuCompilationMessage>,
     ) -> wasmtime::Result<u64> {
-        todo!()
+        Ok(self.table.get(&message)?.line_pos)
     }
 
-    fn offset(&mut self, _self_: Resource<webgpu::GpuCompilationMessage>) -> wasmtime::Result<u64> {
-        todo!()
+    fn offset(
+        &mut self,
+        message: Resource<webgpu::GpuCompilationMessage>,
+    ) -> wasmtime::Result<u64> {
+        Ok(self.table.get(&message)?.offset)
     }
 
-    fn length(&mut self, _self_: Resource<webgpu::GpuCompilationMessage>) -> wasmtime::Result<u64> {
-        todo!()
+    fn length(
+        &mut self,
+        message: Resource<webgpu::GpuCompilationMessage>,
+    ) -> wasmtime::Result<u64> {
+        Ok(self.table.get(&message)?.length)
     }
 
-    fn drop(&mut self, _cm: Resource<webgpu::GpuCompilationMessage>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, message: Resource<webgpu::GpuCompilationMessage>) -> wasmtime::Result<()> {
+        self.table.delete(message)?;
+        Ok(())
     }
 }
 impl<'a> webgpu::HostGpuCompilationInfo for WasiWebGpuCtx<'a> {
     fn messages(
         &mut self,
-        _self_: Resource<webgpu::GpuCompilationInfo>,
+        info: Resource<webgpu::GpuCompilationInfo>,
     ) -> wasmtime::Result<Vec<Resource<webgpu::GpuCompilationMessage>>> {
-        todo!()
+        let messages = self.table.get(&info)?.messages.clone();
+        messages
+            .into_iter()
+            .map(|message| Ok(self.table.push(message)?))
+            .collect()
     }
 
-    fn drop(&mut self, _info: Resource<webgpu::GpuCompilationInfo>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, info: Resource<webgpu::GpuCompilationInfo>) -> wasmtime::Result<()> {
+        self.table.delete(info)?;
+        Ok(())
     }
 }
 impl<'a> webgpu::HostGpuQuerySet for WasiWebGpuCtx<'a> {
-    fn destroy(&mut self, _self_: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
-        // https://github.com/gfx-rs/wgpu/issues/6495
-        todo!()
+    fn destroy(&mut self, query_set: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
+        self.table.get_mut(&query_set)?.destroyed = true;
+        Ok(())
     }
 
     fn type_(
         &mut self,
-        _self_: Resource<webgpu::GpuQuerySet>,
+        query_set: Resource<webgpu::GpuQuerySet>,
     ) -> wasmtime::Result<webgpu::GpuQueryType> {
-        todo!()
+        Ok(self.table.get(&query_set)?.type_)
     }
 
     fn count(
         &mut self,
-        _self_: Resource<webgpu::GpuQuerySet>,
+        query_set: Resource<webgpu::GpuQuerySet>,
     ) -> wasmtime::Result<webgpu::GpuSize32Out> {
-        todo!()
+        Ok(self.table.get(&query_set)?.count)
     }
 
-    fn label(&mut self, _self_: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, query_set: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&query_set)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuQuerySet>,
-        _label: String,
+        query_set: Resource<webgpu::GpuQuerySet>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&query_set)?.label = label;
+        Ok(())
     }
 
-    fn drop(&mut self, _query_set: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
-        let query_set_id = self.table.delete(_query_set)?;
-        self.instance.query_set_drop(query_set_id);
+    fn drop(&mut self, query_set: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
+        let query_set = self.table.delete(query_set)?;
+        self.instance.query_set_drop(query_set.id);
         Ok(())
     }
 }
@@ -2120,57 +2430,71 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
         descriptor: Option<webgpu::GpuRenderBundleDescriptor>,
     ) -> wasmtime::Result<Resource<webgpu::GpuRenderBundle>> {
+        let label = descriptor.as_ref().and_then(|d| d.label.clone());
         let descriptor = descriptor
             .map(|d| d.to_core(self.table))
             .unwrap_or(wgpu_types::RenderBundleDescriptor::default());
-        let mut bundle_encoder_lock = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder_lock.take().unwrap();
-        drop(bundle_encoder_lock);
-        let (render_bundle, err) = self.instance.render_bundle_encoder_finish(
-            bundle_encoder.render_bundle_encoder,
-            &descriptor,
-            None,
-        );
-        bundle_encoder.error_handler.handle_possible_error(err);
-        Ok(self.table.push(render_bundle)?)
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        let error_handler = Arc::clone(&bundle_encoder.error_handler);
+        let render_bundle_encoder = match bundle_encoder.render_bundle_encoder.take() {
+            Some(render_bundle_encoder) => render_bundle_encoder,
+            None => {
+                error_handler
+                    .handle_possible_error(Some(wgpu_core::command::EncoderStateError::Ended));
+                // still need to hand back a bundle, a dummy encoder finishes into an invalid one.
+                wgpu_core::command::RenderBundleEncoder::dummy(bundle_encoder.device)
+            }
+        };
+        let (render_bundle, err) =
+            self.instance
+                .render_bundle_encoder_finish(render_bundle_encoder, &descriptor, None);
+        error_handler.handle_possible_error(err);
+        Ok(self.table.push(RenderBundle::new(render_bundle, label))?)
     }
 
     fn label(
         &mut self,
-        _self_: Resource<webgpu::GpuRenderBundleEncoder>,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
     ) -> wasmtime::Result<String> {
-        todo!()
+        Ok(self.table.get(&bundle_encoder)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuRenderBundleEncoder>,
-        _label: String,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&bundle_encoder)?.label = label;
+        Ok(())
     }
 
     fn push_debug_group(
         &mut self,
-        _bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
         _group_label: String,
     ) -> wasmtime::Result<()> {
-        todo!("Debug markers for RenderBundleEncoder not yet implemented in wgpu")
+        // wgpu doesn't record debug markers in bundles, but using a finished encoder is still an error.
+        self.table.get(&bundle_encoder)?.encode(|_| {});
+        Ok(())
     }
 
     fn pop_debug_group(
         &mut self,
-        _bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
     ) -> wasmtime::Result<()> {
-        todo!("Debug markers for RenderBundleEncoder not yet implemented in wgpu")
+        // see `push_debug_group`.
+        self.table.get(&bundle_encoder)?.encode(|_| {});
+        Ok(())
     }
 
     fn insert_debug_marker(
         &mut self,
-        _bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
         _marker_label: String,
     ) -> wasmtime::Result<()> {
-        todo!("Debug markers for RenderBundleEncoder not yet implemented in wgpu")
+        // see `push_debug_group`.
+        self.table.get(&bundle_encoder)?.encode(|_| {});
+        Ok(())
     }
 
     fn set_bind_group(
@@ -2182,31 +2506,31 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         dynamic_offsets_data_start: Option<webgpu::GpuSize64>,
         dynamic_offsets_data_length: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<Result<(), webgpu::SetBindGroupError>> {
-        let bind_group_id = bind_group.map(|bind_group| *self.table.get(&bind_group).unwrap());
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-
-        // https://www.w3.org/TR/webgpu/#programmable-passes
-        let dynamic_offsets = &dynamic_offsets_data.unwrap_or(vec![]);
-        let mut dynamic_offsets = &dynamic_offsets[..];
-        if let Some(dynamic_offsets_data_start) = dynamic_offsets_data_start {
-            let dynamic_offsets_data_start = dynamic_offsets_data_start as usize;
-            dynamic_offsets = &dynamic_offsets[dynamic_offsets_data_start..];
-        }
-        if let Some(dynamic_offsets_data_length) = dynamic_offsets_data_length {
-            let dynamic_offsets_data_length = dynamic_offsets_data_length as usize;
-            dynamic_offsets = &dynamic_offsets[..dynamic_offsets_data_length];
-        }
-
-        unsafe {
-            wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_bind_group(
-                &mut bundle_encoder.render_bundle_encoder,
-                index,
-                bind_group_id,
-                dynamic_offsets.as_ptr(),
-                dynamic_offsets.len(),
-            )
+        let dynamic_offsets_data = dynamic_offsets_data.unwrap_or_default();
+        let dynamic_offsets = match dynamic_offsets(
+            &dynamic_offsets_data,
+            dynamic_offsets_data_start,
+            dynamic_offsets_data_length,
+        ) {
+            Ok(dynamic_offsets) => dynamic_offsets,
+            Err(e) => return Ok(Err(e)),
         };
+        let bind_group_id = match bind_group {
+            Some(bind_group) => Some(self.table.get(&bind_group)?.id),
+            None => None,
+        };
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            unsafe {
+                wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_bind_group(
+                    bundle_encoder,
+                    index,
+                    bind_group_id,
+                    dynamic_offsets.as_ptr(),
+                    dynamic_offsets.len(),
+                )
+            };
+        });
         Ok(Ok(()))
     }
 
@@ -2217,12 +2541,13 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
     ) -> wasmtime::Result<()> {
         let pipeline = self.table.get(&pipeline)?;
         let pipeline_id = pipeline.render_pipeline_id;
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_pipeline(
-            &mut bundle_encoder.render_bundle_encoder,
-            pipeline_id,
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_pipeline(
+                bundle_encoder,
+                pipeline_id,
+            );
+        });
         Ok(())
     }
 
@@ -2235,16 +2560,24 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         size: Option<webgpu::GpuSize64>,
     ) -> wasmtime::Result<()> {
         let buffer_id = self.table.get(&buffer)?.buffer_id;
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_index_buffer(
-            &mut bundle_encoder.render_bundle_encoder,
-            buffer_id,
-            index_format.into(),
-            offset.unwrap_or(0),
-            size.map(|s| NonZeroU64::new(s).expect("Size can't be zero")),
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        let size = match binding_size(size) {
+            Ok(size) => size,
+            Err(e) => {
+                bundle_encoder.error_handler.handle_possible_error(Some(e));
+                return Ok(());
+            }
+        };
+        bundle_encoder.encode(|bundle_encoder| {
+            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_index_buffer(
+                bundle_encoder,
+                buffer_id,
+                index_format.into(),
+                offset.unwrap_or(0),
+                size,
+            );
+        });
         Ok(())
     }
 
@@ -2258,16 +2591,24 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
     ) -> wasmtime::Result<()> {
         let buffer = buffer.expect("TODO: Null buffers not yet supported in wgpu");
         let buffer_id = self.table.get(&buffer)?.buffer_id;
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_vertex_buffer(
-            &mut bundle_encoder.render_bundle_encoder,
-            slot,
-            buffer_id,
-            offset.unwrap_or(0),
-            size.map(|s| NonZeroU64::new(s).expect("Size can't be zero")),
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        let size = match binding_size(size) {
+            Ok(size) => size,
+            Err(e) => {
+                bundle_encoder.error_handler.handle_possible_error(Some(e));
+                return Ok(());
+            }
+        };
+        bundle_encoder.encode(|bundle_encoder| {
+            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_vertex_buffer(
+                bundle_encoder,
+                slot,
+                buffer_id,
+                offset.unwrap_or(0),
+                size,
+            );
+        });
         Ok(())
     }
 
@@ -2279,16 +2620,17 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         first_vertex: Option<webgpu::GpuSize32>,
         first_instance: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<()> {
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw(
-            &mut bundle_encoder.render_bundle_encoder,
-            vertex_count,
-            instance_count.unwrap_or(1),
-            first_vertex.unwrap_or(0),
-            first_instance.unwrap_or(0),
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw(
+                bundle_encoder,
+                vertex_count,
+                instance_count.unwrap_or(1),
+                first_vertex.unwrap_or(0),
+                first_instance.unwrap_or(0),
+            );
+        });
         Ok(())
     }
 
@@ -2301,17 +2643,18 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         base_vertex: Option<webgpu::GpuSignedOffset32>,
         first_instance: Option<webgpu::GpuSize32>,
     ) -> wasmtime::Result<()> {
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indexed(
-            &mut bundle_encoder.render_bundle_encoder,
-            index_count,
-            instance_count.unwrap_or(1),
-            first_index.unwrap_or(0),
-            base_vertex.unwrap_or(0),
-            first_instance.unwrap_or(0),
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            // https://www.w3.org/TR/webgpu/#gpurendercommandsmixin
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indexed(
+                bundle_encoder,
+                index_count,
+                instance_count.unwrap_or(1),
+                first_index.unwrap_or(0),
+                base_vertex.unwrap_or(0),
+                first_instance.unwrap_or(0),
+            );
+        });
         Ok(())
     }
 
@@ -2322,13 +2665,14 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         indirect_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
         let indirect_buffer = self.table.get(&indirect_buffer)?.buffer_id;
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indirect(
-            &mut bundle_encoder.render_bundle_encoder,
-            indirect_buffer,
-            indirect_offset,
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indirect(
+                bundle_encoder,
+                indirect_buffer,
+                indirect_offset,
+            );
+        });
         Ok(())
     }
 
@@ -2339,25 +2683,42 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
         indirect_offset: webgpu::GpuSize64,
     ) -> wasmtime::Result<()> {
         let indirect_buffer = self.table.get(&indirect_buffer)?.buffer_id;
-        let mut bundle_encoder = self.table.get_mut(&bundle_encoder)?.lock();
-        let bundle_encoder = bundle_encoder.as_mut().unwrap();
-        wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indexed_indirect(
-            &mut bundle_encoder.render_bundle_encoder,
-            indirect_buffer,
-            indirect_offset,
-        );
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|bundle_encoder| {
+            wgpu_core::command::bundle_ffi::wgpu_render_bundle_draw_indexed_indirect(
+                bundle_encoder,
+                indirect_buffer,
+                indirect_offset,
+            );
+        });
         Ok(())
     }
 
     fn set_immediates(
         &mut self,
-        _render_pass: Resource<webgpu::GpuRenderBundleEncoder>,
-        _range_offset: u32,
-        _data: Vec<u8>,
-        _data_offset: Option<u64>,
-        _data_size: Option<u64>,
+        bundle_encoder: Resource<webgpu::GpuRenderBundleEncoder>,
+        range_offset: u32,
+        data: Vec<u8>,
+        data_offset: Option<u64>,
+        data_size: Option<u64>,
     ) -> wasmtime::Result<()> {
-        todo!()
+        let bundle_encoder = self.table.get(&bundle_encoder)?;
+        bundle_encoder.encode(|encoder| {
+            match immediates_data(&data, range_offset, data_offset, data_size) {
+                Ok(data) => unsafe {
+                    wgpu_core::command::bundle_ffi::wgpu_render_bundle_set_immediates(
+                        encoder,
+                        range_offset,
+                        data.len() as u32,
+                        data.as_ptr(),
+                    )
+                },
+                Err(err) => bundle_encoder
+                    .error_handler
+                    .handle_possible_error(Some(err)),
+            }
+        });
+        Ok(())
     }
 
     fn drop(&mut self, encoder: Resource<webgpu::GpuRenderBundleEncoder>) -> wasmtime::Result<()> {
@@ -2366,16 +2727,20 @@ impl<'a> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuCtx<'a> {
     }
 }
 impl<'a> webgpu::HostGpuComputePipeline for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuComputePipeline>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(
+        &mut self,
+        pipeline: Resource<webgpu::GpuComputePipeline>,
+    ) -> wasmtime::Result<String> {
+        Ok(self.table.get(&pipeline)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuComputePipeline>,
-        _label: String,
+        pipeline: Resource<webgpu::GpuComputePipeline>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&pipeline)?.label = label;
+        Ok(())
     }
 
     fn get_bind_group_layout(
@@ -2390,7 +2755,9 @@ impl<'a> webgpu::HostGpuComputePipeline for WasiWebGpuCtx<'a> {
             self.instance
                 .compute_pipeline_get_bind_group_layout(pipeline_id, index, None);
         error_handler.handle_possible_error(err);
-        Ok(self.table.push(bind_group_layout)?)
+        Ok(self
+            .table
+            .push(BindGroupLayout::new(bind_group_layout, None))?)
     }
 
     fn drop(&mut self, pipeline: Resource<webgpu::GpuComputePipeline>) -> wasmtime::Result<()> {
@@ -2401,79 +2768,83 @@ impl<'a> webgpu::HostGpuComputePipeline for WasiWebGpuCtx<'a> {
     }
 }
 impl<'a> webgpu::HostGpuBindGroup for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuBindGroup>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, bind_group: Resource<webgpu::GpuBindGroup>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&bind_group)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuBindGroup>,
-        _label: String,
+        bind_group: Resource<webgpu::GpuBindGroup>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&bind_group)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, bind_group: Resource<webgpu::GpuBindGroup>) -> wasmtime::Result<()> {
-        let bind_group_id = self.table.delete(bind_group)?;
-        self.instance.bind_group_drop(bind_group_id);
+        let bind_group = self.table.delete(bind_group)?;
+        self.instance.bind_group_drop(bind_group.id);
         Ok(())
     }
 }
 impl<'a> webgpu::HostGpuPipelineLayout for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuPipelineLayout>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, layout: Resource<webgpu::GpuPipelineLayout>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&layout)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuPipelineLayout>,
-        _label: String,
+        layout: Resource<webgpu::GpuPipelineLayout>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&layout)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, layout: Resource<webgpu::GpuPipelineLayout>) -> wasmtime::Result<()> {
-        let layout_id = self.table.delete(layout)?;
-        self.instance.pipeline_layout_drop(layout_id);
+        let layout = self.table.delete(layout)?;
+        self.instance.pipeline_layout_drop(layout.id);
         Ok(())
     }
 }
 impl<'a> webgpu::HostGpuBindGroupLayout for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuBindGroupLayout>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, layout: Resource<webgpu::GpuBindGroupLayout>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&layout)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuBindGroupLayout>,
-        _label: String,
+        layout: Resource<webgpu::GpuBindGroupLayout>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&layout)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, layout: Resource<webgpu::GpuBindGroupLayout>) -> wasmtime::Result<()> {
-        let layout_id = self.table.delete(layout)?;
-        self.instance.bind_group_layout_drop(layout_id);
+        let layout = self.table.delete(layout)?;
+        self.instance.bind_group_layout_drop(layout.id);
         Ok(())
     }
 }
 
 impl<'a> webgpu::HostGpuSampler for WasiWebGpuCtx<'a> {
-    fn label(&mut self, _self_: Resource<webgpu::GpuSampler>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, sampler: Resource<webgpu::GpuSampler>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&sampler)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuSampler>,
-        _label: String,
+        sampler: Resource<webgpu::GpuSampler>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&sampler)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, sampler: Resource<webgpu::GpuSampler>) -> wasmtime::Result<()> {
-        let sampler_id = self.table.delete(sampler)?;
-        self.instance.sampler_drop(sampler_id);
+        let sampler = self.table.delete(sampler)?;
+        self.instance.sampler_drop(sampler.id);
         Ok(())
     }
 }
@@ -2510,18 +2881,12 @@ impl<'a> webgpu::HostGpuBuffer for WasiWebGpuCtx<'a> {
         size: Option<webgpu::GpuSize64>,
     ) -> wasmtime::Result<Result<Vec<u8>, webgpu::GetMappedRangeError>> {
         let buffer = self.table.get(&buffer)?;
-        if buffer.map_state != webgpu::GpuBufferMapState::Mapped {
-            todo!("Throw buffer not mapped error");
-        }
-        let buffer_id = buffer.buffer_id;
-        let (ptr, len) = self
-            .instance
-            // https://www.w3.org/TR/webgpu/#gpubuffer
-            .buffer_get_mapped_range(buffer_id, offset.unwrap_or(0), size)?;
+        let (ptr, len) = match mapped_range(self.instance, buffer, offset, size) {
+            Ok(range) => range,
+            Err(e) => return Ok(Err(e)),
+        };
         let data = unsafe { slice::from_raw_parts(ptr.as_ptr(), len as usize) };
-        let mut output = vec![0; len as usize];
-        output.copy_from_slice(data);
-        Ok(Ok(output))
+        Ok(Ok(data.to_vec()))
     }
 
     fn get_mapped_range_set_with_copy(
@@ -2532,16 +2897,22 @@ impl<'a> webgpu::HostGpuBuffer for WasiWebGpuCtx<'a> {
         size: Option<webgpu::GpuSize64>,
     ) -> wasmtime::Result<Result<(), webgpu::GetMappedRangeError>> {
         let buffer = self.table.get(&buffer)?;
-        if buffer.map_state != webgpu::GpuBufferMapState::Mapped {
-            todo!("Throw buffer not mapped error");
+        let (ptr, len) = match mapped_range(self.instance, buffer, offset, size) {
+            Ok(range) => range,
+            Err(e) => return Ok(Err(e)),
+        };
+        // Same as `new Uint8Array(buffer.getMappedRange(offset, size)).set(data)` in JS.
+        if data.len() as u64 > len {
+            return Ok(Err(webgpu::GetMappedRangeError {
+                kind: webgpu::GetMappedRangeErrorKind::RangeError,
+                message: format!(
+                    "data of size {} doesn't fit in mapped range of size {len}",
+                    data.len()
+                ),
+            }));
         }
-        let buffer_id = buffer.buffer_id;
-        let (ptr, len) = self
-            .instance
-            // https://www.w3.org/TR/webgpu/#gpubuffer
-            .buffer_get_mapped_range(buffer_id, offset.unwrap_or(0), size)?;
-        let buffer = unsafe { slice::from_raw_parts_mut(ptr.as_ptr(), len as usize) };
-        buffer.copy_from_slice(&data);
+        let range = unsafe { slice::from_raw_parts_mut(ptr.as_ptr(), len as usize) };
+        range[..data.len()].copy_from_slice(&data);
         Ok(Ok(()))
     }
 
@@ -2550,27 +2921,39 @@ impl<'a> webgpu::HostGpuBuffer for WasiWebGpuCtx<'a> {
         buffer: Resource<webgpu::GpuBuffer>,
     ) -> wasmtime::Result<Result<(), webgpu::UnmapError>> {
         let buffer = self.table.get_mut(&buffer)?;
-        self.instance.buffer_unmap(buffer.buffer_id)?;
+        // wgpu-core aborts a pending map, so its `map_async` resolves with an `AbortError`.
+        match self.instance.buffer_unmap(buffer.buffer_id) {
+            // https://www.w3.org/TR/webgpu/#dom-gpubuffer-unmap
+            // > 1. If this.[[pending_map]] is null and this.[[mapping]] is null, return.
+            // Unmapping a destroyed buffer is a no-op too, since destroy already unmapped it.
+            Ok(())
+            | Err(wgpu_core::resource::BufferAccessError::NotMapped)
+            | Err(wgpu_core::resource::BufferAccessError::DestroyedResource(_)) => {}
+            Err(e) => buffer.error_handler.handle_possible_error(Some(e)),
+        }
         buffer.map_state = webgpu::GpuBufferMapState::Unmapped;
         Ok(Ok(()))
     }
 
     fn destroy(&mut self, buffer: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<()> {
-        let buffer_id = self.table.get_mut(&buffer)?.buffer_id;
-        self.instance.buffer_destroy(buffer_id);
+        let buffer = self.table.get_mut(&buffer)?;
+        // wgpu-core unmaps the buffer, and aborts any pending map.
+        self.instance.buffer_destroy(buffer.buffer_id);
+        buffer.map_state = webgpu::GpuBufferMapState::Unmapped;
         Ok(())
     }
 
-    fn label(&mut self, _self_: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<String> {
-        todo!()
+    fn label(&mut self, buffer: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<String> {
+        Ok(self.table.get(&buffer)?.label.clone())
     }
 
     fn set_label(
         &mut self,
-        _self_: Resource<webgpu::GpuBuffer>,
-        _label: String,
+        buffer: Resource<webgpu::GpuBuffer>,
+        label: String,
     ) -> wasmtime::Result<()> {
-        todo!()
+        self.table.get_mut(&buffer)?.label = label;
+        Ok(())
     }
 
     fn drop(&mut self, buffer: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<()> {
@@ -2596,52 +2979,105 @@ impl<T: Send> webgpu::HostGpuBufferWithStore<T> for crate::HasWasiWebGpuCtx {
         // >     3. Generate a validation error.
 
         let mode = if mode == webgpu::GpuMapMode::READ {
-            wgpu_core::device::HostMap::Read
+            Some(wgpu_core::device::HostMap::Read)
         } else if mode == webgpu::GpuMapMode::WRITE {
-            wgpu_core::device::HostMap::Write
+            Some(wgpu_core::device::HostMap::Write)
         } else {
-            // TODO: return webgpu::MapAsyncError
-            bail!("validation error")
+            None
         };
 
         // https://www.w3.org/TR/webgpu/#gpubuffer
         let offset = offset.unwrap_or(0);
 
-        accessor
-            .with(|mut access| -> wasmtime::Result<_> {
-                let ctx = access.get();
-                let instance = Arc::clone(ctx.instance);
-                let buffer = ctx.table.get_mut(&buffer)?;
-                let buffer_id = buffer.buffer_id;
+        let map = accessor.with(|mut access| -> wasmtime::Result<_> {
+            let ctx = access.get();
+            let instance = Arc::clone(ctx.instance);
+            let buffer = ctx.table.get_mut(&buffer)?;
+
+            // https://www.w3.org/TR/webgpu/#dom-gpubuffer-mapasync
+            // > 2. If this.[[pending_map]] is not null:
+            // >     1. Reject p with an OperationError.
+            if buffer.map_state == webgpu::GpuBufferMapState::Pending {
+                return Ok(Err(webgpu::MapAsyncError {
+                    kind: webgpu::MapAsyncErrorKind::OperationError,
+                    message: "buffer already has a pending map".to_string(),
+                }));
+            }
+            let Some(mode) = mode else {
+                buffer
+                    .error_handler
+                    .handle_possible_error(Some(ValidationError(
+                        "map mode must contain exactly one of READ or WRITE".to_string(),
+                    )));
+                return Ok(Err(webgpu::MapAsyncError {
+                    kind: webgpu::MapAsyncErrorKind::OperationError,
+                    message: "invalid map mode".to_string(),
+                }));
+            };
+
+            let buffer_id = buffer.buffer_id;
+            let poller = Arc::clone(&buffer.poller);
+            let error_handler = Arc::clone(&buffer.error_handler);
+            // Mapping an already mapped buffer fails validation in wgpu-core, and leaves the existing mapping alone.
+            if buffer.map_state == webgpu::GpuBufferMapState::Unmapped {
                 buffer.map_state = webgpu::GpuBufferMapState::Pending;
+            }
 
-                type Callback =
-                    Box<dyn FnOnce(Box<Result<(), wgpu_core::resource::BufferAccessError>>) + Send>;
-                Ok(CallbackFuture::new(Box::new(move |resolve: Callback| {
-                    let op = wgpu_core::resource::BufferMapOperation {
-                        host: mode,
-                        callback: Some(Box::new(move |result| {
-                            resolve(Box::new(result));
-                        })),
-                    };
-                    instance
-                        .buffer_map_async(buffer_id, offset, size, op)
-                        .unwrap();
-                    // TODO: only poll this device.
-                    instance.poll_all_devices(true).unwrap();
-                })))
-            })?
-            .await
-            .unwrap();
+            type Callback =
+                Box<dyn FnOnce(Box<Result<(), wgpu_core::resource::BufferAccessError>>) + Send>;
+            let future = CallbackFuture::new(Box::new(move |resolve: Callback| {
+                let op = wgpu_core::resource::BufferMapOperation {
+                    host: mode,
+                    callback: Some(Box::new(move |result| {
+                        resolve(Box::new(result));
+                    })),
+                };
+                // Validation errors are passed to the callback as well, so they're handled below.
+                if instance
+                    .buffer_map_async(buffer_id, offset, size, op)
+                    .is_ok()
+                {
+                    poller.poll();
+                }
+            }));
+            Ok(Ok((future, error_handler)))
+        })?;
+        let (future, error_handler) = match map {
+            Ok(map) => map,
+            Err(e) => return Ok(Err(e)),
+        };
+
+        let result = *future.await;
 
         accessor.with(|mut access| -> wasmtime::Result<_> {
             let ctx = access.get();
             let buffer = ctx.table.get_mut(&buffer)?;
-            buffer.map_state = webgpu::GpuBufferMapState::Mapped;
-            Ok(())
-        })?;
-
-        Ok(Ok(()))
+            match result {
+                Ok(()) => {
+                    buffer.map_state = webgpu::GpuBufferMapState::Mapped;
+                    Ok(Ok(()))
+                }
+                // `unmap` or `destroy` was called while the map was pending, and they already reset the map state.
+                // https://www.w3.org/TR/webgpu/#abort-a-pending-map
+                Err(wgpu_core::resource::BufferAccessError::MapAborted) => {
+                    Ok(Err(webgpu::MapAsyncError {
+                        kind: webgpu::MapAsyncErrorKind::AbortError,
+                        message: "map was aborted by unmap or destroy".to_string(),
+                    }))
+                }
+                Err(e) => {
+                    if buffer.map_state == webgpu::GpuBufferMapState::Pending {
+                        buffer.map_state = webgpu::GpuBufferMapState::Unmapped;
+                    }
+                    let message = e.to_string();
+                    error_handler.handle_possible_error(Some(e));
+                    Ok(Err(webgpu::MapAsyncError {
+                        kind: webgpu::MapAsyncErrorKind::OperationError,
+                        message,
+                    }))
+                }
+            }
+        })
     }
 }
 
@@ -3168,3 +3604,722 @@ impl<'a> webgpu::HostGpuSupportedLimits for WasiWebGpuCtx<'a> {
         Ok(())
     }
 }
+
+// wgpu-core resolves timestamp queries to raw ticks, unless the instance normalizes them to the nanoseconds WebGPU expects.
+// `timestamp-query` is only exposed to guests when the instance does, since guests have no way to ask for the timestamp period.
+fn guest_features(
+    instance: &wgpu_core::global::Global,
+    features: wgpu_types::Features,
+) -> wgpu_types::Features {
+    if instance
+        .instance
+        .flags
+        .contains(wgpu_types::InstanceFlags::AUTOMATIC_TIMESTAMP_NORMALIZATION)
+    {
+        features
+    } else {
+        features - wgpu_types::Features::TIMESTAMP_QUERY
+    }
+}
+
+// immediates are only gated behind maxImmediateSize in WebGPU, but wgpu-core also needs the `IMMEDIATES` feature for them.
+// Asking for immediates from an adapter without the feature is like asking for a better limit than the adapter has.
+fn enable_immediates(
+    descriptor: &mut wgpu_types::DeviceDescriptor<wgpu_core::Label>,
+    adapter_features: wgpu_types::Features,
+) -> Result<(), webgpu::RequestDeviceError> {
+    if descriptor.required_limits.max_immediate_size == 0 {
+        return Ok(());
+    }
+    if !adapter_features.contains(wgpu_types::Features::IMMEDIATES) {
+        // From the spec:
+        // > 2. All of the requirements in the following steps must be met.
+        // >  2. For each [key, value] in descriptor.requiredLimits for which value is not undefined:
+        // >   2. value must be no better than adapter.[[limits]][key].
+        // > 3. If any are unmet, issue the following steps on contentTimeline and return:
+        // >  1. Reject promise with an OperationError.
+        return Err(webgpu::RequestDeviceError {
+            kind: webgpu::RequestDeviceErrorKind::OperationError,
+            message: "maxImmediateSize needs an adapter that supports immediates".to_string(),
+        });
+    }
+    descriptor.required_features |= wgpu_types::Features::IMMEDIATES;
+    Ok(())
+}
+
+// https://www.w3.org/TR/webgpu/#programmable-passes
+// The content timeline checks of `setBindGroup`, which throw a RangeError instead of generating a validation error.
+fn dynamic_offsets(
+    data: &[webgpu::GpuBufferDynamicOffset],
+    start: Option<webgpu::GpuSize64>,
+    length: Option<webgpu::GpuSize32>,
+) -> Result<&[webgpu::GpuBufferDynamicOffset], webgpu::SetBindGroupError> {
+    let range_error = |message: String| webgpu::SetBindGroupError {
+        kind: webgpu::SetBindGroupErrorKind::RangeError,
+        message,
+    };
+    let start = start.unwrap_or(0);
+    let rest = usize::try_from(start)
+        .ok()
+        .and_then(|start| data.get(start..))
+        .ok_or_else(|| {
+            range_error(format!(
+                "dynamic offsets start {start} is out of bounds of {} offsets",
+                data.len()
+            ))
+        })?;
+    match length {
+        None => Ok(rest),
+        Some(length) => rest.get(..length as usize).ok_or_else(|| {
+            range_error(format!(
+                "dynamic offsets start {start} and length {length} are out of bounds of {} offsets",
+                data.len()
+            ))
+        }),
+    }
+}
+
+// wgpu-core takes `None` for the rest of the buffer, so there's no way to bind zero bytes.
+fn binding_size(size: Option<webgpu::GpuSize64>) -> Result<Option<NonZeroU64>, ValidationError> {
+    size.map(|size| {
+        NonZeroU64::new(size)
+            .ok_or_else(|| ValidationError("Buffer binding size can't be zero".to_string()))
+    })
+    .transpose()
+}
+
+// https://www.w3.org/TR/webgpu/#dom-gpubuffer-getmappedrange
+// The content timeline checks from the spec, which throw instead of generating validation errors. wgpu-core checks the range itself.
+fn mapped_range(
+    instance: &wgpu_core::global::Global,
+    buffer: &Buffer,
+    offset: Option<webgpu::GpuSize64>,
+    size: Option<webgpu::GpuSize64>,
+) -> Result<(std::ptr::NonNull<u8>, u64), webgpu::GetMappedRangeError> {
+    if buffer.map_state != webgpu::GpuBufferMapState::Mapped {
+        return Err(webgpu::GetMappedRangeError {
+            kind: webgpu::GetMappedRangeErrorKind::OperationError,
+            message: "buffer is not mapped".to_string(),
+        });
+    }
+    // https://www.w3.org/TR/webgpu/#gpubuffer
+    instance
+        .buffer_get_mapped_range(buffer.buffer_id, offset.unwrap_or(0), size)
+        .map_err(|e| webgpu::GetMappedRangeError {
+            kind: webgpu::GetMappedRangeErrorKind::OperationError,
+            message: e.to_string(),
+        })
+}
+
+// https://www.w3.org/TR/webgpu/#dom-gpubindingcommandsmixin-setimmediates
+// Picks out the bytes of `data` to upload. Alignment is checked here as well, since wgpu-core's render bundle encoder panics on unaligned offsets and sizes.
+fn immediates_data(
+    data: &[u8],
+    range_offset: u32,
+    data_offset: Option<u64>,
+    data_size: Option<u64>,
+) -> Result<&[u8], wgpu_core::binding_model::ImmediateUploadError> {
+    use wgpu_core::binding_model::ImmediateUploadError;
+
+    let data_offset = data_offset.unwrap_or(0);
+    if data_offset > data.len() as u64 {
+        return Err(ImmediateUploadError::ValueStartIndexOverrun {
+            start_index: data_offset as u32,
+            data_size: data.len(),
+        });
+    }
+    let size = data_size.unwrap_or(data.len() as u64 - data_offset);
+    if data_offset
+        .checked_add(size)
+        .is_none_or(|end| end > data.len() as u64)
+    {
+        return Err(ImmediateUploadError::ValueEndIndexOverrun {
+            start_index: data_offset as u32,
+            count: size as u32,
+            data_size: data.len(),
+        });
+    }
+    if !range_offset.is_multiple_of(wgpu_types::IMMEDIATE_DATA_ALIGNMENT) {
+        return Err(ImmediateUploadError::StartOffsetUnaligned(range_offset));
+    }
+    if !size.is_multiple_of(wgpu_types::IMMEDIATE_DATA_ALIGNMENT as u64) {
+        return Err(ImmediateUploadError::SizeUnaligned(size as u32));
+    }
+    Ok(&data[data_offset as usize..(data_offset + size) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::tests::{borrow, noop_ctx};
+    use webgpu::{
+        HostGpuBuffer, HostGpuCanvasContext, HostGpuCommandEncoder, HostGpuDevice, HostGpuQuerySet,
+        HostGpuQueue, HostGpuRenderBundleEncoder, HostGpuRenderPassEncoder, HostGpuTexture,
+    };
+
+    #[test]
+    fn dynamic_offsets_bounds() {
+        let data = [0, 256, 512];
+        assert_eq!(dynamic_offsets(&data, None, None).unwrap(), &data);
+        assert_eq!(dynamic_offsets(&data, Some(1), None).unwrap(), &[256, 512]);
+        assert_eq!(dynamic_offsets(&data, Some(1), Some(1)).unwrap(), &[256]);
+        assert!(dynamic_offsets(&data, Some(3), Some(0)).unwrap().is_empty());
+
+        for (start, length) in [(Some(4), None), (Some(1), Some(3)), (Some(u64::MAX), None)] {
+            let error = dynamic_offsets(&data, start, length).unwrap_err();
+            assert!(matches!(
+                error.kind,
+                webgpu::SetBindGroupErrorKind::RangeError
+            ));
+        }
+    }
+
+    #[test]
+    fn timestamp_query_needs_normalization() {
+        let (noop, _) = noop_ctx();
+        let features = wgpu_types::Features::TIMESTAMP_QUERY | wgpu_types::Features::SHADER_F16;
+        assert_eq!(
+            guest_features(&noop.instance, features),
+            wgpu_types::Features::SHADER_F16
+        );
+    }
+
+    #[test]
+    fn queue_label_is_shared() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let mut ctx = noop.ctx();
+        let queue = HostGpuDevice::queue(&mut ctx, borrow(&device))?;
+        HostGpuQueue::set_label(&mut ctx, borrow(&queue), "queue".to_string())?;
+        let again = HostGpuDevice::queue(&mut ctx, borrow(&device))?;
+        assert_eq!(HostGpuQueue::label(&mut ctx, borrow(&again))?, "queue");
+        Ok(())
+    }
+
+    #[test]
+    fn texture_descriptor_getters() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let mut ctx = noop.ctx();
+
+        let texture = HostGpuDevice::create_texture(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuTextureDescriptor {
+                size: webgpu::GpuExtent3D {
+                    width: 4,
+                    height: Some(2),
+                    depth_or_array_layers: Some(3),
+                },
+                mip_level_count: Some(2),
+                sample_count: None,
+                dimension: None,
+                format: webgpu::GpuTextureFormat::Rgba8unorm,
+                usage: webgpu::GpuTextureUsage::TEXTURE_BINDING | webgpu::GpuTextureUsage::COPY_DST,
+                view_formats: None,
+                texture_binding_view_dimension: Some(webgpu::GpuTextureViewDimension::D2Array),
+                label: None,
+            },
+        )?;
+        assert_eq!(HostGpuTexture::width(&mut ctx, borrow(&texture))?, 4);
+        assert_eq!(HostGpuTexture::height(&mut ctx, borrow(&texture))?, 2);
+        assert_eq!(
+            HostGpuTexture::depth_or_array_layers(&mut ctx, borrow(&texture))?,
+            3
+        );
+        assert_eq!(
+            HostGpuTexture::mip_level_count(&mut ctx, borrow(&texture))?,
+            2
+        );
+        // omitted members get their defaults.
+        assert_eq!(HostGpuTexture::sample_count(&mut ctx, borrow(&texture))?, 1);
+        assert_eq!(
+            HostGpuTexture::dimension(&mut ctx, borrow(&texture))?,
+            webgpu::GpuTextureDimension::D2
+        );
+        assert_eq!(
+            HostGpuTexture::format(&mut ctx, borrow(&texture))?,
+            webgpu::GpuTextureFormat::Rgba8unorm
+        );
+        assert_eq!(
+            HostGpuTexture::usage(&mut ctx, borrow(&texture))?,
+            webgpu::GpuTextureUsage::TEXTURE_BINDING | webgpu::GpuTextureUsage::COPY_DST
+        );
+        assert_eq!(
+            HostGpuTexture::texture_binding_view_dimension(&mut ctx, borrow(&texture))?,
+            Some(webgpu::GpuTextureViewDimension::D2Array)
+        );
+
+        // surface textures take their descriptor from the surface configuration.
+        let texture_id = ctx.table.get(&texture)?.texture_id;
+        let config = wgpu_types::SurfaceConfiguration {
+            usage: wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu_types::TextureFormat::Bgra8Unorm,
+            width: 640,
+            height: 480,
+            present_mode: wgpu_types::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu_types::CompositeAlphaMode::Opaque,
+            view_formats: Vec::new(),
+        };
+        // SAFETY: the texture is only used for its getters, not as a surface texture.
+        let surface_texture =
+            unsafe { ctx.table.get(&device)?.connect_texture(texture_id, &config) };
+        let surface_texture = ctx.table.push(surface_texture)?;
+        assert_eq!(
+            HostGpuTexture::width(&mut ctx, borrow(&surface_texture))?,
+            640
+        );
+        assert_eq!(
+            HostGpuTexture::height(&mut ctx, borrow(&surface_texture))?,
+            480
+        );
+        assert_eq!(
+            HostGpuTexture::format(&mut ctx, borrow(&surface_texture))?,
+            webgpu::GpuTextureFormat::Bgra8unorm
+        );
+        assert_eq!(
+            HostGpuTexture::usage(&mut ctx, borrow(&surface_texture))?,
+            webgpu::GpuTextureUsage::RENDER_ATTACHMENT
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn immediates_data_bounds() {
+        use wgpu_core::binding_model::ImmediateUploadError;
+
+        let data = [0u8, 1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(immediates_data(&data, 0, None, None).unwrap(), &data);
+        assert_eq!(
+            immediates_data(&data, 4, Some(4), None).unwrap(),
+            &data[4..]
+        );
+        assert_eq!(
+            immediates_data(&data, 0, Some(0), Some(4)).unwrap(),
+            &data[..4]
+        );
+        assert!(immediates_data(&data, 0, Some(8), None).unwrap().is_empty());
+
+        assert!(matches!(
+            immediates_data(&data, 0, Some(9), None),
+            Err(ImmediateUploadError::ValueStartIndexOverrun { .. })
+        ));
+        assert!(matches!(
+            immediates_data(&data, 0, Some(4), Some(8)),
+            Err(ImmediateUploadError::ValueEndIndexOverrun { .. })
+        ));
+        assert!(matches!(
+            immediates_data(&data, 0, Some(4), Some(u64::MAX)),
+            Err(ImmediateUploadError::ValueEndIndexOverrun { .. })
+        ));
+        assert!(matches!(
+            immediates_data(&data, 2, None, None),
+            Err(ImmediateUploadError::StartOffsetUnaligned(2))
+        ));
+        assert!(matches!(
+            immediates_data(&data, 0, Some(2), None),
+            Err(ImmediateUploadError::SizeUnaligned(6))
+        ));
+    }
+
+    #[test]
+    fn immediates_need_adapter_support() {
+        let mut descriptor = wgpu_types::DeviceDescriptor::default();
+        enable_immediates(&mut descriptor, wgpu_types::Features::empty()).unwrap();
+        assert!(descriptor.required_features.is_empty());
+
+        descriptor.required_limits.max_immediate_size = 64;
+        let error = enable_immediates(&mut descriptor, wgpu_types::Features::empty()).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            webgpu::RequestDeviceErrorKind::OperationError
+        ));
+        assert!(descriptor.required_features.is_empty());
+
+        enable_immediates(&mut descriptor, wgpu_types::Features::IMMEDIATES).unwrap();
+        assert_eq!(
+            descriptor.required_features,
+            wgpu_types::Features::IMMEDIATES
+        );
+    }
+
+    #[test]
+    fn submitted_work_done_resolves() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let mut ctx = noop.ctx();
+        let encoder = HostGpuDevice::create_command_encoder(&mut ctx, borrow(&device), None)?;
+        let command_buffer = HostGpuCommandEncoder::finish(&mut ctx, borrow(&encoder), None)?;
+        let queue = HostGpuDevice::queue(&mut ctx, borrow(&device))?;
+        HostGpuQueue::submit(&mut ctx, borrow(&queue), vec![borrow(&command_buffer)])?;
+
+        let queue = ctx.table.get(&queue)?;
+        let done = submitted_work_done(
+            Arc::clone(ctx.instance),
+            *queue.id,
+            Arc::clone(&queue.poller),
+        );
+        // waits on another thread, so a future that never resolves fails the test instead of hanging it.
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            futures::executor::block_on(done);
+            let _ = sender.send(());
+        });
+        receiver.recv_timeout(std::time::Duration::from_secs(10))?;
+        Ok(())
+    }
+
+    #[test]
+    fn zero_binding_size() {
+        assert_eq!(binding_size(None).unwrap(), None);
+        assert_eq!(binding_size(Some(4)).unwrap(), NonZeroU64::new(4));
+        assert!(binding_size(Some(0)).is_err());
+    }
+
+    #[test]
+    fn resolve_occlusion_queries() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let error_handler = Arc::clone(noop.table.get(&device)?.error_handler());
+        let mut ctx = noop.ctx();
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+
+        let query_set = HostGpuDevice::create_query_set(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuQuerySetDescriptor {
+                type_: webgpu::GpuQueryType::Occlusion,
+                count: 2,
+                label: None,
+            },
+        )?
+        .unwrap();
+        assert_eq!(
+            HostGpuQuerySet::type_(&mut ctx, borrow(&query_set))?,
+            webgpu::GpuQueryType::Occlusion
+        );
+        assert_eq!(HostGpuQuerySet::count(&mut ctx, borrow(&query_set))?, 2);
+
+        let texture = HostGpuDevice::create_texture(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuTextureDescriptor {
+                size: webgpu::GpuExtent3D {
+                    width: 4,
+                    height: Some(4),
+                    depth_or_array_layers: None,
+                },
+                mip_level_count: None,
+                sample_count: None,
+                dimension: None,
+                format: webgpu::GpuTextureFormat::Rgba8unorm,
+                usage: webgpu::GpuTextureUsage::RENDER_ATTACHMENT,
+                view_formats: None,
+                texture_binding_view_dimension: None,
+                label: None,
+            },
+        )?;
+        let view = HostGpuTexture::create_view(&mut ctx, borrow(&texture), None)?;
+        let resolve_buffer = HostGpuDevice::create_buffer(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuBufferDescriptor {
+                size: 2 * 8,
+                usage: webgpu::GpuBufferUsage::QUERY_RESOLVE | webgpu::GpuBufferUsage::COPY_SRC,
+                mapped_at_creation: None,
+                label: None,
+            },
+        )?;
+
+        let encoder = HostGpuDevice::create_command_encoder(&mut ctx, borrow(&device), None)?;
+        let render_pass = HostGpuCommandEncoder::begin_render_pass(
+            &mut ctx,
+            borrow(&encoder),
+            webgpu::GpuRenderPassDescriptor {
+                color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                    view: borrow(&view),
+                    depth_slice: None,
+                    resolve_target: None,
+                    clear_value: None,
+                    load_op: webgpu::GpuLoadOp::Clear,
+                    store_op: webgpu::GpuStoreOp::Store,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: Some(borrow(&query_set)),
+                timestamp_writes: None,
+                max_draw_count: None,
+                label: None,
+            },
+        )?;
+        for query_index in 0..2 {
+            HostGpuRenderPassEncoder::begin_occlusion_query(
+                &mut ctx,
+                borrow(&render_pass),
+                query_index,
+            )?;
+            HostGpuRenderPassEncoder::end_occlusion_query(&mut ctx, borrow(&render_pass))?;
+        }
+        HostGpuRenderPassEncoder::end(&mut ctx, borrow(&render_pass))?;
+        HostGpuCommandEncoder::resolve_query_set(
+            &mut ctx,
+            borrow(&encoder),
+            borrow(&query_set),
+            0,
+            2,
+            borrow(&resolve_buffer),
+            0,
+        )?;
+        let command_buffer = HostGpuCommandEncoder::finish(&mut ctx, borrow(&encoder), None)?;
+        let queue = HostGpuDevice::queue(&mut ctx, borrow(&device))?;
+        HostGpuQueue::submit(&mut ctx, borrow(&queue), vec![borrow(&command_buffer)])?;
+        assert!(error_handler.pop_scope().unwrap().is_none());
+
+        // after `destroy`, using the query set is a validation error.
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+        HostGpuQuerySet::destroy(&mut ctx, borrow(&query_set))?;
+        let encoder = HostGpuDevice::create_command_encoder(&mut ctx, borrow(&device), None)?;
+        HostGpuCommandEncoder::resolve_query_set(
+            &mut ctx,
+            borrow(&encoder),
+            borrow(&query_set),
+            0,
+            2,
+            borrow(&resolve_buffer),
+            0,
+        )?;
+        HostGpuCommandEncoder::finish(&mut ctx, borrow(&encoder), None)?;
+        let error = error_handler.pop_scope().unwrap().unwrap();
+        assert!(matches!(error.kind, webgpu::GpuErrorKind::ValidationError));
+        Ok(())
+    }
+
+    #[test]
+    fn buffer_mapping_errors() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let error_handler = Arc::clone(noop.table.get(&device)?.error_handler());
+        let mut ctx = noop.ctx();
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+
+        let buffer = HostGpuDevice::create_buffer(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuBufferDescriptor {
+                size: 16,
+                usage: webgpu::GpuBufferUsage::COPY_DST | webgpu::GpuBufferUsage::COPY_SRC,
+                mapped_at_creation: Some(true),
+                label: None,
+            },
+        )?;
+        let error = HostGpuBuffer::get_mapped_range_set_with_copy(
+            &mut ctx,
+            borrow(&buffer),
+            vec![0; 32],
+            None,
+            None,
+        )?
+        .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            webgpu::GetMappedRangeErrorKind::RangeError
+        ));
+        HostGpuBuffer::get_mapped_range_set_with_copy(
+            &mut ctx,
+            borrow(&buffer),
+            vec![1; 8],
+            Some(8),
+            None,
+        )?
+        .unwrap();
+        let data = HostGpuBuffer::get_mapped_range_get_with_copy(
+            &mut ctx,
+            borrow(&buffer),
+            Some(8),
+            None,
+        )?
+        .unwrap();
+        assert_eq!(data, vec![1; 8]);
+
+        HostGpuBuffer::unmap(&mut ctx, borrow(&buffer))?.unwrap();
+        let error =
+            HostGpuBuffer::get_mapped_range_get_with_copy(&mut ctx, borrow(&buffer), None, None)?
+                .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            webgpu::GetMappedRangeErrorKind::OperationError
+        ));
+        // unmapping an unmapped buffer is a no-op.
+        HostGpuBuffer::unmap(&mut ctx, borrow(&buffer))?.unwrap();
+
+        let queue = HostGpuDevice::queue(&mut ctx, borrow(&device))?;
+        let error = HostGpuQueue::write_buffer_with_copy(
+            &mut ctx,
+            borrow(&queue),
+            borrow(&buffer),
+            0,
+            vec![0; 8],
+            Some(9),
+            None,
+        )?
+        .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            webgpu::WriteBufferErrorKind::OperationError
+        ));
+        let error = HostGpuQueue::write_buffer_with_copy(
+            &mut ctx,
+            borrow(&queue),
+            borrow(&buffer),
+            0,
+            vec![0; 8],
+            None,
+            Some(3),
+        )?
+        .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            webgpu::WriteBufferErrorKind::OperationError
+        ));
+        HostGpuQueue::write_buffer_with_copy(
+            &mut ctx,
+            borrow(&queue),
+            borrow(&buffer),
+            8,
+            vec![0; 8],
+            Some(4),
+            Some(4),
+        )?
+        .unwrap();
+        assert!(error_handler.pop_scope().unwrap().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn render_bundle_debug_markers() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let error_handler = Arc::clone(noop.table.get(&device)?.error_handler());
+        let mut ctx = noop.ctx();
+
+        let bundle_encoder = HostGpuDevice::create_render_bundle_encoder(
+            &mut ctx,
+            borrow(&device),
+            webgpu::GpuRenderBundleEncoderDescriptor {
+                depth_read_only: None,
+                stencil_read_only: None,
+                color_formats: vec![Some(webgpu::GpuTextureFormat::Rgba8unorm)],
+                depth_stencil_format: None,
+                sample_count: None,
+                label: None,
+            },
+        )?;
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+        HostGpuRenderBundleEncoder::push_debug_group(
+            &mut ctx,
+            borrow(&bundle_encoder),
+            "group".to_string(),
+        )?;
+        HostGpuRenderBundleEncoder::insert_debug_marker(
+            &mut ctx,
+            borrow(&bundle_encoder),
+            "marker".to_string(),
+        )?;
+        HostGpuRenderBundleEncoder::pop_debug_group(&mut ctx, borrow(&bundle_encoder))?;
+        HostGpuRenderBundleEncoder::finish(&mut ctx, borrow(&bundle_encoder), None)?;
+        assert!(error_handler.pop_scope().unwrap().is_none());
+
+        // a finished encoder reports an error instead of trapping.
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+        HostGpuRenderBundleEncoder::insert_debug_marker(
+            &mut ctx,
+            borrow(&bundle_encoder),
+            "marker".to_string(),
+        )?;
+        assert!(error_handler.pop_scope().unwrap().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn offscreen_canvas() -> wasmtime::Result<()> {
+        let (mut noop, device) = noop_ctx();
+        let context = noop.table.push(crate::CanvasContext::new(2, 2))?;
+        let mut ctx = noop.ctx();
+
+        let configuration = |format| webgpu::GpuCanvasConfiguration {
+            device: borrow(&device),
+            format,
+            usage: None,
+            view_formats: None,
+            color_space: None,
+            tone_mapping: None,
+            alpha_mode: None,
+        };
+        assert!(HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context)).is_err());
+
+        // an unsupported format is a validation error on the device, instead of trapping.
+        let error_handler = Arc::clone(ctx.table.get(&device)?.error_handler());
+        error_handler.push_scope(webgpu::GpuErrorFilter::Validation);
+        HostGpuCanvasContext::configure(
+            &mut ctx,
+            borrow(&context),
+            configuration(webgpu::GpuTextureFormat::R8unorm),
+        )?;
+        let error = error_handler.pop_scope().unwrap().unwrap();
+        assert!(matches!(error.kind, webgpu::GpuErrorKind::ValidationError));
+        assert!(HostGpuCanvasContext::get_configuration(&mut ctx, borrow(&context))?.is_none());
+
+        HostGpuCanvasContext::configure(
+            &mut ctx,
+            borrow(&context),
+            configuration(webgpu::GpuTextureFormat::Rgba8unorm),
+        )?;
+        let configuration =
+            HostGpuCanvasContext::get_configuration(&mut ctx, borrow(&context))?.unwrap();
+        assert_eq!(configuration.format, webgpu::GpuTextureFormat::Rgba8unorm);
+
+        // the same texture is handed out until the frame is presented.
+        let texture = HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context))?;
+        let again = HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context))?;
+        let texture_id = ctx.table.get(&texture)?.texture_id;
+        assert_eq!(ctx.table.get(&again)?.texture_id, texture_id);
+        assert_eq!(HostGpuTexture::width(&mut ctx, borrow(&texture))?, 2);
+        HostGpuTexture::drop(&mut ctx, again)?;
+
+        // nothing presented yet reads back as a cleared frame.
+        let frame = ctx
+            .table
+            .get(&context)?
+            .read_presented_frame(ctx.instance)?
+            .unwrap();
+        assert_eq!(frame.data, [0, 0, 0, 255].repeat(4));
+
+        // presenting moves on to the next texture of the ring, and wraps around.
+        ctx.table.get_mut(&context)?.present();
+        let next = HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context))?;
+        assert_ne!(ctx.table.get(&next)?.texture_id, texture_id);
+        for _ in 1..crate::FRAME_COUNT {
+            HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context))?;
+            ctx.table.get_mut(&context)?.present();
+        }
+        let wrapped = HostGpuCanvasContext::get_current_texture(&mut ctx, borrow(&context))?;
+        assert_eq!(ctx.table.get(&wrapped)?.texture_id, texture_id);
+
+        // `alpha-mode` defaults to opaque.
+        let frame = ctx
+            .table
+            .get(&context)?
+            .read_presented_frame(ctx.instance)?
+            .unwrap();
+        assert_eq!((frame.width, frame.height), (2, 2));
+        assert_eq!(frame.data, [0, 0, 0, 255].repeat(4));
+
+        // a presented frame can be read back after the context is resized and unconfigured.
+        let presented = ctx
+            .table
+            .get(&context)?
+            .presented_frame(ctx.instance)
+            .unwrap();
+        ctx.table.get_mut(&context)?.resize(ctx.instance, 4, 4);
+        HostGpuCanvasContext::unconfigure(&mut ctx, borrow(&context))?;
+        assert!(HostGpuCanvasContext::get_configuration(&mut ctx, borrow(&context))?.is_none());
+        let frame = presented.read()?;
+        assert_eq!((frame.width, frame.height), (2, 2));
+        assert_eq!(frame.data, [0, 0, 0, 255].repeat(4));
+        Ok(())
+    }
+}
@@ -4,10 +4,13 @@
 use std::{
     collections::HashMap,
     fmt::{self, Debug},
-    sync::{Arc, Mutex},
+    future::Future,
+    sync::{mpsc, Arc, Mutex},
 };
 
-use crate::wasi::webgpu::webgpu;
+use futures::{channel::oneshot, future::Shared, FutureExt};
+
+use crate::{canvas_context::SharedTextureId, wasi::webgpu::webgpu};
 
 pub struct WgslLanguageFeatures;
 impl WgslLanguageFeatures {
@@ -28,12 +31,240 @@ impl WgslLanguageFeatures {
 pub type RecordGpuPipelineConstantValue = HashMap<String, webgpu::GpuPipelineConstantValue>;
 pub type RecordOptionGpuSize64 = HashMap<String, Option<webgpu::GpuSize64>>;
 
+// label needed for `label`/`set_label` on resources that would otherwise be a plain wgpu id.
+// wgpu-core gets the label at creation time, but has no way to read it back or to change it afterwards, so `set_label` only updates our copy.
+// TODO: these should be unit-structs instead of `types` so that the internals are private to the crate
+pub type TextureView = Labeled<wgpu_core::id::TextureViewId>;
+pub type Sampler = Labeled<wgpu_core::id::SamplerId>;
+pub type BindGroup = Labeled<wgpu_core::id::BindGroupId>;
+pub type BindGroupLayout = Labeled<wgpu_core::id::BindGroupLayoutId>;
+pub type PipelineLayout = Labeled<wgpu_core::id::PipelineLayoutId>;
+pub type CommandBuffer = Labeled<wgpu_core::id::CommandBufferId>;
+pub type RenderBundle = Labeled<wgpu_core::id::RenderBundleId>;
+
+#[derive(Debug)]
+pub struct Labeled<T> {
+    pub(crate) id: T,
+    pub(crate) label: String,
+}
+impl<T> Labeled<T> {
+    pub(crate) fn new(id: T, label: Option<String>) -> Self {
+        Self {
+            id,
+            // https://www.w3.org/TR/webgpu/#dom-gpuobjectdescriptorbase-label
+            label: label.unwrap_or_default(),
+        }
+    }
+}
+
+pub struct ShaderModule {
+    pub(crate) id: wgpu_core::id::ShaderModuleId,
+    pub(crate) label: String,
+    // wgpu-core only reports shader diagnostics as the error returned from `device_create_shader_module`, so they're captured at creation time for `get_compilation_info`.
+    pub(crate) compilation_info: CompilationInfo,
+}
+
+// https://www.w3.org/TR/webgpu/#gpucompilationinfo
+#[derive(Clone, Debug, Default)]
+pub struct CompilationInfo {
+    pub(crate) messages: Vec<CompilationMessage>,
+}
+impl CompilationInfo {
+    // The first label of a naga error points at the error itself, the others point at related code and are reported as `info` messages.
+    // naga doesn't hand warnings to wgpu-core, so there are never any `warning` messages.
+    pub(crate) fn from_error(error: &wgpu_core::pipeline::CreateShaderModuleError) -> Self {
+        use wgpu_core::pipeline::CreateShaderModuleError;
+        let messages = match error {
+            CreateShaderModuleError::Parsing(error) => Self::labeled(
+                error.inner.message().to_string(),
+                &error.source,
+                error.inner.labels(),
+            ),
+            CreateShaderModuleError::Validation(error) => Self::labeled(
+                error.inner.to_string(),
+                &error.source,
+                error
+                    .inner
+                    .spans()
+                    .map(|(span, label)| (*span, label.as_str())),
+            ),
+            error => vec![CompilationMessage::new(
+                error.to_string(),
+                webgpu::GpuCompilationMessageType::Error,
+                "",
+                None,
+            )],
+        };
+        Self { messages }
+    }
+
+    fn labeled<'a>(
+        message: String,
+        source: &str,
+        mut labels: impl Iterator<Item = (wgpu_core::naga::Span, &'a str)>,
+    ) -> Vec<CompilationMessage> {
+        let error = CompilationMessage::new(
+            message,
+            webgpu::GpuCompilationMessageType::Error,
+            source,
+            labels.next().map(|(span, _)| span),
+        );
+        std::iter::once(error)
+            .chain(labels.map(|(span, label)| {
+                CompilationMessage::new(
+                    label.to_string(),
+                    webgpu::GpuCompilationMessageType::Info,
+                    source,
+                    Some(span),
+                )
+            }))
+            .collect()
+    }
+}
+
+// https://www.w3.org/TR/webgpu/#gpucompilationmessage
+#[derive(Clone, Debug)]
+pub struct CompilationMessage {
+    pub(crate) message: String,
+    pub(crate) message_type: webgpu::GpuCompilationMessageType,
+    pub(crate) line_num: u64,
+    pub(crate) line_pos: u64,
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
+}
+impl CompilationMessage {
+    fn new(
+        message: String,
+        message_type: webgpu::GpuCompilationMessageType,
+        source: &str,
+        span: Option<wgpu_core::naga::Span>,
+    ) -> Self {
+        // naga spans are byte ranges, but the spec wants positions in UTF-16 code units.
+        // Messages without a (valid) span get all zeros, as the spec says to do when there's no location.
+        let range = span
+            .and_then(|span| span.to_range())
+            .filter(|range| source.get(range.clone()).is_some());
+        let (line_num, line_pos, offset, length) = match range {
+            Some(range) => {
+                let prefix = &source[..range.start];
+                let line_start = prefix.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+                (
+                    prefix.matches('\n').count() as u64 + 1,
+                    prefix[line_start..].encode_utf16().count() as u64 + 1,
+                    prefix.encode_utf16().count() as u64,
+                    source[range].encode_utf16().count() as u64,
+                )
+            }
+            None => (0, 0, 0, 0),
+        };
+        Self {
+            message,
+            message_type,
+            line_num,
+            line_pos,
+            offset,
+            length,
+        }
+    }
+}
+
+// type and count needed for `GpuQuerySet.type`/`GpuQuerySet.count`.
+// wgpu-core can't destroy a query set (https://github.com/gfx-rs/wgpu/issues/6495), so `destroy` only marks it as destroyed, and it's dropped along with the resource.
+pub struct QuerySet {
+    pub(crate) id: wgpu_core::id::QuerySetId,
+    pub(crate) destroyed: bool,
+    pub(crate) label: String,
+    pub(crate) type_: webgpu::GpuQueryType,
+    pub(crate) count: u32,
+}
+impl QuerySet {
+    // Using a destroyed query set is a validation error.
+    pub(crate) fn validate(&self) -> Option<ValidationError> {
+        self.destroyed
+            .then(|| ValidationError(format!("query set {:?} is destroyed", self.label)))
+    }
+}
+
 // RenderPassEncoder, ComputePassEncoder, and RenderBundleEncoder need to be dropped when calling `.end`/`.finish` on them, but we can't guarantee that they'll be dropped in time by GC languages. Takeable lets you take the value and leaves None in place, so that RenderPass/ComputePass get dropped from Rust's point of view, but the wasm module can keep it's reference.
 // this is caused by the same underlying issue as this one https://github.com/gfx-rs/wgpu-native/issues/412
-// TODO: these should be unit-structs instead of `types` so that the internals are private to the crate
-pub type RenderPassEncoder = Takeable<wgpu_core::command::RenderPass>;
-pub type ComputePassEncoder = Takeable<wgpu_core::command::ComputePass>;
-pub type RenderBundleEncoder = Takeable<RenderBundleEncoderInner>;
+// error_handler needed so that misuse (e.g. encoding after `.end`) is reported as a WebGPU error instead of trapping.
+pub struct RenderPassEncoder {
+    pub(crate) render_pass: Takeable<wgpu_core::command::RenderPass>,
+    pub(crate) label: String,
+    pub(crate) error_handler: Arc<ErrorHandler>,
+}
+pub struct ComputePassEncoder {
+    pub(crate) compute_pass: Takeable<wgpu_core::command::ComputePass>,
+    pub(crate) label: String,
+    pub(crate) error_handler: Arc<ErrorHandler>,
+}
+// device needed to create a replacement encoder when `.finish` is called more than once.
+pub struct RenderBundleEncoder {
+    pub(crate) render_bundle_encoder: Takeable<wgpu_core::command::RenderBundleEncoder>,
+    pub(crate) device: wgpu_core::id::DeviceId,
+    pub(crate) label: String,
+    pub(crate) error_handler: Arc<ErrorHandler>,
+}
+
+impl RenderPassEncoder {
+    pub(crate) fn new(
+        render_pass: wgpu_core::command::RenderPass,
+        label: Option<String>,
+        error_handler: Arc<ErrorHandler>,
+    ) -> Self {
+        Self {
+            render_pass: Takeable::new(render_pass),
+            label: label.unwrap_or_default(),
+            error_handler,
+        }
+    }
+
+    pub(crate) fn encode<E: wgpu_types::error::WebGpuError + fmt::Display>(
+        &self,
+        f: impl FnOnce(&mut wgpu_core::command::RenderPass) -> Result<(), E>,
+    ) {
+        self.render_pass.encode(&self.error_handler, f);
+    }
+}
+impl ComputePassEncoder {
+    pub(crate) fn new(
+        compute_pass: wgpu_core::command::ComputePass,
+        label: Option<String>,
+        error_handler: Arc<ErrorHandler>,
+    ) -> Self {
+        Self {
+            compute_pass: Takeable::new(compute_pass),
+            label: label.unwrap_or_default(),
+            error_handler,
+        }
+    }
+
+    pub(crate) fn encode<E: wgpu_types::error::WebGpuError + fmt::Display>(
+        &self,
+        f: impl FnOnce(&mut wgpu_core::command::ComputePass) -> Result<(), E>,
+    ) {
+        self.compute_pass.encode(&self.error_handler, f);
+    }
+}
+impl RenderBundleEncoder {
+    pub(crate) fn new(
+        render_bundle_encoder: wgpu_core::command::RenderBundleEncoder,
+        label: Option<String>,
+        error_handler: Arc<ErrorHandler>,
+    ) -> Self {
+        Self {
+            device: render_bundle_encoder.parent(),
+            render_bundle_encoder: Takeable::new(render_bundle_encoder),
+            label: label.unwrap_or_default(),
+            error_handler,
+        }
+    }
+
+    // bundle commands are only validated in `.finish`, so there's no error to report here.
+    pub(crate) fn encode(&self, f: impl FnOnce(&mut wgpu_core::command::RenderBundleEncoder)) {
+        self.render_bundle_encoder.with(&self.error_handler, f);
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Takeable<T: std::fmt::Debug>(Arc<std::sync::Mutex<Option<T>>>);
@@ -47,78 +278,258 @@ where
     pub(crate) fn lock<'a>(&'a self) -> std::sync::MutexGuard<'a, Option<T>> {
         self.0.lock().unwrap()
     }
-    // pub(crate) fn take(&self) -> Option<T> {
-    //     self.0.lock().unwrap().take()
-    // }
+    pub(crate) fn take(&self) -> Option<T> {
+        self.0.lock().unwrap().take()
+    }
+
+    /// Runs `f` on the value, reporting an error if it was already taken by `.end`/`.finish`.
+    pub(crate) fn with(&self, error_handler: &ErrorHandler, f: impl FnOnce(&mut T)) {
+        match self.lock().as_mut() {
+            Some(value) => f(value),
+            None => error_handler
+                .handle_possible_error(Some(wgpu_core::command::EncoderStateError::Ended)),
+        }
+    }
+
+    /// Like `with`, but also reports the error returned by `f`.
+    pub(crate) fn encode<E: wgpu_types::error::WebGpuError + fmt::Display>(
+        &self,
+        error_handler: &ErrorHandler,
+        f: impl FnOnce(&mut T) -> Result<(), E>,
+    ) {
+        self.with(error_handler, |value| {
+            error_handler.handle_possible_error(f(value).err())
+        });
+    }
 }
 
 // size needed in `GpuBuffer.size`, `RenderPass.set_index_buffer`, `RenderPass.set_vertex_buffer`.
 // usage needed in `GpuBuffer.usage`
+// poller needed to get `GpuBuffer.map_async` callbacks called.
 pub struct Buffer {
     pub(crate) buffer_id: wgpu_core::id::BufferId,
+    pub(crate) label: String,
     pub(crate) size: u64,
     pub(crate) usage: wgpu_types::BufferUsages,
     pub(crate) map_state: webgpu::GpuBufferMapState,
+    pub(crate) poller: Arc<DevicePoller>,
+    pub(crate) error_handler: Arc<ErrorHandler>,
 }
 
 // references to queue and adapter are also saved in device.
 // TODO: these should be unit-structs instead of `types` so that the internals are private to the crate
-pub type Queue = Arc<wgpu_core::id::QueueId>;
 pub type Adapter = Arc<wgpu_core::id::AdapterId>;
 
+// poller needed to get `GpuQueue.on_submitted_work_done` callbacks called.
+// label shared with the device, every `Device.queue` call returns the same queue from the guest's point of view.
+pub struct Queue {
+    pub(crate) id: Arc<wgpu_core::id::QueueId>,
+    pub(crate) poller: Arc<DevicePoller>,
+    pub(crate) error_handler: Arc<ErrorHandler>,
+    pub(crate) label: Arc<Mutex<String>>,
+}
+
+// wgpu-core only calls map, submitted work done, and device lost callbacks from `device_poll`, and waiting on a device blocks.
+// Each device gets its own thread to wait on it, so that one guest waiting on its device doesn't stall the async runtime, or other devices sharing the same `Global`.
+// The thread also owns the device id, and drops the device once every handle to the poller is gone, so it never polls a dropped device.
+#[derive(Debug)]
+pub(crate) struct DevicePoller {
+    sender: mpsc::Sender<()>,
+}
+impl DevicePoller {
+    fn new(instance: Arc<wgpu_core::global::Global>, device: wgpu_core::id::DeviceId) -> Self {
+        let (sender, receiver) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("wgpu device poller".to_string())
+            .spawn(move || {
+                while receiver.recv().is_ok() {
+                    // requests that came in while polling are covered by the next poll.
+                    while receiver.try_recv().is_ok() {}
+                    if let Err(e) =
+                        instance.device_poll(device, wgpu_types::PollType::wait_indefinitely())
+                    {
+                        log::warn!("device poll failed: {e}");
+                    }
+                }
+                instance.device_drop(device);
+            })
+            .expect("failed to spawn device poller thread");
+        Self { sender }
+    }
+
+    /// Waits for all work submitted to the device to finish on the poller thread, calling any callbacks that are ready along the way.
+    pub(crate) fn poll(&self) {
+        // the thread only exits once `self` is dropped.
+        let _ = self.sender.send(());
+    }
+}
+
 // queue needed for Device.queue
 // adapter needed for surface_get_capabilities in connect_graphics_context
 // keeping queue and adapter as Arc for reference counting while dropping.
+// lost needed for Device.lost, since wgpu-core only lets us register a single device lost closure.
+// poller shared with the device's queues and buffers, the device is dropped along with the last one of them.
 #[derive(Clone)]
 pub struct Device {
     pub(crate) device: wgpu_core::id::DeviceId,
+    pub(crate) poller: Arc<DevicePoller>,
     pub(crate) queue: Arc<wgpu_core::id::QueueId>,
     pub(crate) adapter: Arc<wgpu_core::id::AdapterId>,
     pub(crate) error_handler: Arc<ErrorHandler>,
+    pub(crate) lost: Shared<oneshot::Receiver<DeviceLostInfo>>,
+    pub(crate) label: String,
+    // shared with every `GpuQueue` created through `Device.queue`.
+    pub(crate) queue_label: Arc<Mutex<String>>,
 }
 impl Device {
+    pub(crate) fn new(
+        instance: &Arc<wgpu_core::global::Global>,
+        device: wgpu_core::id::DeviceId,
+        queue: wgpu_core::id::QueueId,
+        adapter: Arc<wgpu_core::id::AdapterId>,
+        label: Option<String>,
+        queue_label: Option<String>,
+    ) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        instance.device_set_device_lost_closure(
+            device,
+            Box::new(move |reason, message| {
+                // receiver is gone if every clone of this device was dropped, nobody to notify.
+                let _ = sender.send(DeviceLostInfo {
+                    reason: reason.into(),
+                    message,
+                });
+            }),
+        );
+        Self {
+            device,
+            poller: Arc::new(DevicePoller::new(Arc::clone(instance), device)),
+            queue: Arc::new(queue),
+            adapter,
+            error_handler: Arc::new(ErrorHandler::default()),
+            lost: receiver.shared(),
+            label: label.unwrap_or_default(),
+            queue_label: Arc::new(Mutex::new(queue_label.unwrap_or_default())),
+        }
+    }
+
+    /// Resolves once the device is lost, either through `destroy` or because wgpu-core lost it.
+    pub(crate) fn lost(&self) -> impl Future<Output = DeviceLostInfo> + Send + Unpin + 'static {
+        self.lost.clone().map(|info| {
+            // wgpu-core drops the device lost closure without calling it when the device is
+            // dropped before being lost, in which case the device is effectively lost for unknown reasons.
+            info.unwrap_or_else(|_| DeviceLostInfo {
+                reason: webgpu::GpuDeviceLostReason::Unknown,
+                message: "device was dropped".to_string(),
+            })
+        })
+    }
+
     pub fn device_id(&self) -> &wgpu_core::id::DeviceId {
         &self.device
     }
 
+    pub fn adapter_id(&self) -> &wgpu_core::id::AdapterId {
+        &self.adapter
+    }
+
+    /// Errors reported here reach the guest through error scopes and `onuncapturederror`, like errors from wgpu calls made by the guest.
+    pub fn error_handler(&self) -> &Arc<ErrorHandler> {
+        &self.error_handler
+    }
+
+    /// Number of nanoseconds per tick of the timestamps written by `timestamp` query sets.
+    /// Unless the instance was created with `InstanceFlags::AUTOMATIC_TIMESTAMP_NORMALIZATION` (in which case this is `1.0`), wgpu-core resolves timestamp queries to raw ticks rather than the nanoseconds WebGPU expects.
+    /// `wasi:webgpu` has no way to ask for the period, so guests only get the `timestamp-query` feature from instances that normalize.
+    pub fn timestamp_period(&self, instance: &wgpu_core::global::Global) -> f32 {
+        instance.queue_get_timestamp_period(*self.queue)
+    }
+
     /// Create a `Texture` from a `TextureId` that is connected to this device.
     /// Useful in cases where an external crate get a texture through get_current_texture
     /// and needs to connect it to a device.
     ///
+    /// `config` is the configuration of the surface the texture came from, and is used to answer the `GpuTexture` attribute getters.
+    ///
     /// # Safety
     ///
     /// The caller must ensure that the TextureId is actually connected to this device
     #[doc(hidden)]
-    pub unsafe fn connect_texture(&self, texture_id: wgpu_core::id::TextureId) -> Texture {
+    pub unsafe fn connect_texture(
+        &self,
+        texture_id: wgpu_core::id::TextureId,
+        config: &wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>,
+    ) -> Texture {
         Texture {
             texture_id,
+            label: String::new(),
+            // surface textures are always single sampled 2d textures with one mip level.
+            descriptor: wgpu_types::TextureDescriptor {
+                label: (),
+                size: wgpu_types::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu_types::TextureDimension::D2,
+                format: config.format,
+                usage: config.usage,
+                view_formats: config.view_formats.clone(),
+            },
+            texture_binding_view_dimension: None,
             error_handler: Arc::clone(&self.error_handler),
+            shared_id: None,
         }
     }
+
+    /// Drop a clone of the device that was taken out of the resource table.
+    /// Every clone of a device holds on to the adapter and queue, the last one to be released drops them.
+    pub fn release(self, instance: &wgpu_core::global::Global) {
+        // the wgpu-core device itself is dropped by its poller, once its queues and buffers are gone too.
+        if let Some(adapter_id) = Arc::into_inner(self.adapter) {
+            instance.adapter_drop(adapter_id);
+        }
+        if let Some(queue_id) = Arc::into_inner(self.queue) {
+            instance.queue_drop(queue_id);
+        }
+    }
+}
+
+// wgpu_types::DeviceLostReason can't be converted to a resource directly, and the message needs to be kept around.
+#[derive(Clone, Debug)]
+pub struct DeviceLostInfo {
+    pub(crate) reason: webgpu::GpuDeviceLostReason,
+    pub(crate) message: String,
 }
 
 #[derive(Clone)]
 pub struct CommandEncoder {
     pub(crate) command_encoder_id: wgpu_core::id::CommandEncoderId,
+    pub(crate) label: String,
     pub(crate) error_handler: Arc<ErrorHandler>,
 }
 
 pub struct Texture {
     pub(crate) texture_id: wgpu_core::id::TextureId,
+    pub(crate) label: String,
+    // wgpu-core has no getters for a texture's creation parameters, so we keep the descriptor around for the `GpuTexture` attributes.
+    pub(crate) descriptor: wgpu_types::TextureDescriptor<(), Vec<wgpu_types::TextureFormat>>,
+    pub(crate) texture_binding_view_dimension: Option<webgpu::GpuTextureViewDimension>,
     pub(crate) error_handler: Arc<ErrorHandler>,
+    // set for textures handed out by a `GpuCanvasContext`, which shares the texture id with the guest.
+    pub(crate) shared_id: Option<Arc<SharedTextureId>>,
 }
 pub struct RenderPipeline {
     pub(crate) render_pipeline_id: wgpu_core::id::RenderPipelineId,
+    pub(crate) label: String,
     pub(crate) error_handler: Arc<ErrorHandler>,
 }
 
-#[derive(Debug)]
-pub struct RenderBundleEncoderInner {
-    pub(crate) render_bundle_encoder: wgpu_core::command::RenderBundleEncoder,
-    pub(crate) error_handler: Arc<ErrorHandler>,
-}
 pub struct ComputePipeline {
     pub(crate) compute_pipeline_id: wgpu_core::id::ComputePipelineId,
+    pub(crate) label: String,
     pub(crate) error_handler: Arc<ErrorHandler>,
 }
 
@@ -128,9 +539,50 @@ pub struct GpuError {
     pub(crate) kind: webgpu::GpuErrorKind,
 }
 
+// Some wgpu-core errors (e.g. from `command_encoder_finish`) hand back the label of the failing object separately instead of including it in their message.
+#[derive(Debug)]
+pub(crate) struct LabeledError<E> {
+    pub(crate) label: String,
+    pub(crate) error: E,
+}
+impl<E: fmt::Display> fmt::Display for LabeledError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.label.is_empty() {
+            write!(f, "{}", self.error)
+        } else {
+            write!(f, "{} (label: {:?})", self.error, self.label)
+        }
+    }
+}
+impl<E: std::error::Error + 'static> std::error::Error for LabeledError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl<E: wgpu_types::error::WebGpuError> wgpu_types::error::WebGpuError for LabeledError<E> {
+    fn webgpu_error_type(&self) -> wgpu_types::error::ErrorType {
+        self.error.webgpu_error_type()
+    }
+}
+
+// Validation that the spec requires but wgpu-core can't do for us, e.g. because the wasi-webgpu types are looser than the wgpu-core ones.
+#[derive(Debug)]
+pub struct ValidationError(pub String);
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for ValidationError {}
+impl wgpu_types::error::WebGpuError for ValidationError {
+    fn webgpu_error_type(&self) -> wgpu_types::error::ErrorType {
+        wgpu_types::error::ErrorType::Validation
+    }
+}
+
 // Device level error handler
 #[derive(Debug)]
-pub(crate) struct ErrorHandler(Mutex<ErrorHandlerInner>);
+pub struct ErrorHandler(Mutex<ErrorHandlerInner>);
 
 #[derive(Debug)]
 pub(crate) struct ErrorHandlerInner {
@@ -192,6 +644,11 @@ impl ErrorHandler {
         error: Option<E>,
     ) {
         if let Some(error) = error {
+            // Operations on a lost device don't surface errors, the loss is reported through `GpuDevice.lost` instead.
+            // https://www.w3.org/TR/webgpu/#lose-the-device
+            if error.webgpu_error_type() == wgpu_types::error::ErrorType::DeviceLost {
+                return;
+            }
             let error_kind = error.webgpu_error_type().into();
             let error = GpuError {
                 message: error.to_string(),
@@ -254,3 +711,128 @@ impl From<webgpu::GpuErrorKind> for webgpu::GpuErrorFilter {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Creates a `Device` on the noop backend, so tests can run without a GPU.
+    pub(crate) fn noop_device() -> (Arc<wgpu_core::global::Global>, Device) {
+        let instance = Arc::new(wgpu_core::global::Global::new(
+            "webgpu-test",
+            wgpu_types::InstanceDescriptor {
+                backends: wgpu_types::Backends::NOOP,
+                flags: wgpu_types::InstanceFlags::default(),
+                backend_options: wgpu_types::BackendOptions {
+                    noop: wgpu_types::NoopBackendOptions { enable: true },
+                    ..Default::default()
+                },
+                memory_budget_thresholds: Default::default(),
+                display: None,
+            },
+            None,
+        ));
+        let adapter = instance
+            .request_adapter(&Default::default(), wgpu_types::Backends::NOOP, None)
+            .unwrap();
+        let (device, queue) = instance
+            .adapter_request_device(adapter, &Default::default(), None, None)
+            .unwrap();
+        let device = Device::new(&instance, device, queue, Arc::new(adapter), None, None);
+        (instance, device)
+    }
+
+    /// Owns what a `WasiWebGpuCtx` borrows, for tests that call the `Host*` traits directly.
+    pub(crate) struct NoopCtx {
+        pub(crate) instance: Arc<wgpu_core::global::Global>,
+        pub(crate) table: wasmtime_wasi::ResourceTable,
+    }
+
+    impl NoopCtx {
+        pub(crate) fn ctx(&mut self) -> crate::WasiWebGpuCtx<'_> {
+            crate::WasiWebGpuCtx {
+                instance: &self.instance,
+                table: &mut self.table,
+            }
+        }
+    }
+
+    /// Like `noop_device`, with the device pushed into a fresh resource table.
+    pub(crate) fn noop_ctx() -> (NoopCtx, wasmtime::component::Resource<Device>) {
+        let (instance, device) = noop_device();
+        let mut table = wasmtime_wasi::ResourceTable::new();
+        let device = table.push(device).unwrap();
+        (NoopCtx { instance, table }, device)
+    }
+
+    /// Borrows an owned resource, the way the guest passes it to a method.
+    pub(crate) fn borrow<T: 'static>(
+        resource: &wasmtime::component::Resource<T>,
+    ) -> wasmtime::component::Resource<T> {
+        wasmtime::component::Resource::new_borrow(resource.rep())
+    }
+
+    #[test]
+    fn compilation_messages() {
+        // non-ASCII characters before the error, which take 2 and 4 bytes in UTF-8, and 1 and 2 code units in UTF-16.
+        let source = "// é😀\nfn a() {}\nfn a() {}";
+        let error = wgpu_core::naga::front::wgsl::parse_str(source).unwrap_err();
+        let info =
+            CompilationInfo::from_error(&wgpu_core::pipeline::CreateShaderModuleError::Parsing(
+                wgpu_core::naga::error::ShaderError {
+                    source: source.to_string(),
+                    label: None,
+                    inner: Box::new(error),
+                },
+            ));
+        let positions = info
+            .messages
+            .iter()
+            .map(|m| (m.message_type, m.line_num, m.line_pos, m.offset, m.length))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                (webgpu::GpuCompilationMessageType::Error, 3, 4, 20, 1),
+                (webgpu::GpuCompilationMessageType::Info, 2, 4, 10, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn poller_drops_device() {
+        let (instance, device) = noop_device();
+        let devices = || instance.generate_report().hub.devices.num_kept_from_user;
+        assert_eq!(devices(), 1);
+
+        // a clone keeps the poller, and with it the device, alive.
+        let clone = device.clone();
+        device.release(&instance);
+        clone.poller.poll();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(devices(), 1);
+
+        // the poller thread drops the device once the last handle is gone.
+        clone.release(&instance);
+        let start = std::time::Instant::now();
+        while devices() != 0 {
+            assert!(
+                start.elapsed() < std::time::Duration::from_secs(10),
+                "device wasn't dropped"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn destroy_resolves_lost() {
+        let (instance, device) = noop_device();
+        let lost = device.lost();
+        instance.device_destroy(device.device);
+        // the lost closure fires from poll, as in `GpuDevice.destroy`.
+        let _ = instance.device_poll(device.device, wgpu_types::PollType::Poll);
+        let info = futures::executor::block_on(lost);
+        assert_eq!(info.reason, webgpu::GpuDeviceLostReason::Destroyed);
+        assert_eq!(info.message, "");
+    }
+}
@@ -1,10 +1,11 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use clap::Parser;
 use frame_buffer_wasmtime::{FrameBufferCtx, FrameBufferCtxView};
+use surface_wasmtime::winit::EventLoopExit;
 use surface_wasmtime::{
-    winit::WasiWinitEventLoopProxy, SurfaceCtxView, SurfaceFrameBufferCtx,
-    SurfaceFrameBufferCtxView, SurfaceWebgpuCtx, SurfaceWebgpuCtxView,
+    headless::HeadlessSpawner, InputRecording, MainThreadSpawner, PresentPolicy, SurfaceCtxView,
+    SurfaceFrameBufferCtx, SurfaceFrameBufferCtxView, SurfaceWebgpuCtx, SurfaceWebgpuCtxView,
 };
 use wasi_webgpu_wasmtime::{WasiWebGpuCtx, WasiWebGpuCtxView};
 use wasmtime::{
@@ -20,8 +21,17 @@ struct RuntimeArgs {
     /// The example name
     #[arg(long)]
     example: String,
+    /// Record input delivered to the surfaces to this file, as JSON lines
+    #[arg(long)]
+    record_input: Option<std::path::PathBuf>,
+    /// Replay input recorded with `--record-input`, on headless surfaces instead of windows
+    #[arg(long, conflicts_with = "record_input")]
+    replay_input: Option<std::path::PathBuf>,
 }
 
+// how long a replayed guest gets to create its surfaces, and to receive each frame.
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(10);
+
 wasmtime::component::bindgen!({
     path: "../wit/",
     world: "example",
@@ -31,19 +41,21 @@ wasmtime::component::bindgen!({
     require_store_data_send: true,
 });
 
-struct HostState {
+struct HostState<S> {
     instance: Arc<wgpu_core::global::Global>,
-    main_thread_proxy: Arc<surface_wasmtime::winit::WasiWinitEventLoopProxy>,
+    main_thread_proxy: Arc<S>,
 }
 
-impl HostState {
-    fn new(main_thread_proxy: surface_wasmtime::winit::WasiWinitEventLoopProxy) -> Self {
+impl<S: MainThreadSpawner> HostState<S> {
+    fn new(main_thread_proxy: S) -> Self {
         Self {
             instance: Arc::new(wgpu_core::global::Global::new(
                 "webgpu",
                 wgpu_types::InstanceDescriptor {
                     backends: wgpu_types::Backends::all(),
-                    flags: wgpu_types::InstanceFlags::from_build_config(),
+                    // guests expect timestamp queries in nanoseconds.
+                    flags: wgpu_types::InstanceFlags::from_build_config()
+                        | wgpu_types::InstanceFlags::AUTOMATIC_TIMESTAMP_NORMALIZATION,
                     backend_options: Default::default(),
                     memory_budget_thresholds: Default::default(),
                     display: None,
@@ -53,26 +65,28 @@ impl HostState {
             main_thread_proxy: Arc::new(main_thread_proxy),
         }
     }
-    pub fn add_workload(&self) -> WorkloadState {
+    pub fn add_workload(&self) -> WorkloadState<S> {
         WorkloadState {
             table: ResourceTable::new(),
             instance: Arc::clone(&self.instance),
             main_thread_proxy: Arc::clone(&self.main_thread_proxy),
+            present_policy: PresentPolicy::default(),
         }
     }
 }
 
-struct WorkloadState {
+struct WorkloadState<S> {
     table: ResourceTable,
     instance: Arc<wgpu_core::global::Global>,
-    main_thread_proxy: Arc<WasiWinitEventLoopProxy>,
+    main_thread_proxy: Arc<S>,
+    present_policy: PresentPolicy,
 }
 
-impl wasmtime::component::HasData for WorkloadState {
-    type Data<'a> = &'a mut WorkloadState;
+impl<S: MainThreadSpawner> wasmtime::component::HasData for WorkloadState<S> {
+    type Data<'a> = &'a mut WorkloadState<S>;
 }
 
-impl WasiWebGpuCtxView for WorkloadState {
+impl<S: MainThreadSpawner> WasiWebGpuCtxView for WorkloadState<S> {
     fn webgpu_ctx(&mut self) -> WasiWebGpuCtx<'_> {
         WasiWebGpuCtx {
             instance: &self.instance,
@@ -81,7 +95,7 @@ impl WasiWebGpuCtxView for WorkloadState {
     }
 }
 
-impl FrameBufferCtxView for WorkloadState {
+impl<S: MainThreadSpawner> FrameBufferCtxView for WorkloadState<S> {
     fn frame_buffer_ctx<'a>(&'a mut self) -> FrameBufferCtx<'a> {
         FrameBufferCtx {
             table: &mut self.table,
@@ -89,28 +103,29 @@ impl FrameBufferCtxView for WorkloadState {
     }
 }
 
-impl SurfaceCtxView for WorkloadState {
-    type Spawner = WasiWinitEventLoopProxy;
-    fn surface_ctx(&mut self) -> surface_wasmtime::SurfaceCtx<'_, WasiWinitEventLoopProxy> {
+impl<S: MainThreadSpawner> SurfaceCtxView for WorkloadState<S> {
+    type Spawner = S;
+    fn surface_ctx(&mut self) -> surface_wasmtime::SurfaceCtx<'_, S> {
         surface_wasmtime::SurfaceCtx {
             table: &mut self.table,
             main_thread_spawner: &self.main_thread_proxy,
         }
     }
 }
-impl SurfaceWebgpuCtxView for WorkloadState {
-    type Spawner = WasiWinitEventLoopProxy;
-    fn surface_webgpu_ctx(&mut self) -> SurfaceWebgpuCtx<'_, WasiWinitEventLoopProxy> {
+impl<S: MainThreadSpawner> SurfaceWebgpuCtxView for WorkloadState<S> {
+    type Spawner = S;
+    fn surface_webgpu_ctx(&mut self) -> SurfaceWebgpuCtx<'_, S> {
         SurfaceWebgpuCtx {
             table: &mut self.table,
             instance: &self.instance,
             main_thread_spawner: &self.main_thread_proxy,
+            present_policy: &self.present_policy,
         }
     }
 }
-impl SurfaceFrameBufferCtxView for WorkloadState {
-    type Spawner = WasiWinitEventLoopProxy;
-    fn surface_frame_buffer_ctx(&mut self) -> SurfaceFrameBufferCtx<'_, WasiWinitEventLoopProxy> {
+impl<S: MainThreadSpawner> SurfaceFrameBufferCtxView for WorkloadState<S> {
+    type Spawner = S;
+    fn surface_frame_buffer_ctx(&mut self) -> SurfaceFrameBufferCtx<'_, S> {
         SurfaceFrameBufferCtx {
             table: &mut self.table,
             instance: &self.instance,
@@ -119,7 +134,7 @@ impl SurfaceFrameBufferCtxView for WorkloadState {
     }
 }
 
-impl ExampleImports for WorkloadState {
+impl<S: MainThreadSpawner> ExampleImports for WorkloadState<S> {
     fn print(&mut self, s: String) {
         println!("{s}");
     }
@@ -133,26 +148,79 @@ async fn main() -> anyhow::Result<()> {
 
     let args = RuntimeArgs::parse();
 
-    let (main_thread_loop, main_thread_proxy) =
+    if let Some(path) = &args.replay_input {
+        let recording = InputRecording::load(path).context("Failed to load input recording")?;
+        let spawner = HeadlessSpawner::new();
+        // the guest is stopped once the replay is done.
+        start_guest(&args.example, HostState::new(spawner.clone()), || {}).await?;
+        // surfaces are numbered in order of creation, same as when recording.
+        let surface_count = recording.events.iter().map(|e| e.surface + 1).max();
+        return tokio::task::spawn_blocking(move || {
+            std::thread::scope(|scope| {
+                let replays: Vec<_> = (0..surface_count.unwrap_or(0))
+                    .map(|index| {
+                        let surface = spawner
+                            .wait_for_surface(index, REPLAY_TIMEOUT)
+                            .with_context(|| format!("Guest didn't create surface {index}"))?;
+                        let recording = &recording;
+                        Ok(scope.spawn(move || recording.replay(&surface, index, REPLAY_TIMEOUT)))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                for replay in replays {
+                    replay.join().unwrap()?;
+                }
+                anyhow::Ok(())
+            })
+        })
+        .await?;
+    }
+
+    let (mut main_thread_loop, main_thread_proxy) =
         surface_wasmtime::winit::create_wasi_winit_event_loop();
-    let host_state = HostState::new(main_thread_proxy);
+    if let Some(path) = &args.record_input {
+        let recorder = surface_wasmtime::InputRecorder::create(path)
+            .context("Failed to create input recording")?;
+        main_thread_loop.record_input(recorder);
+    }
+    let exit_proxy = main_thread_proxy.clone();
+    let guest = start_guest(
+        &args.example,
+        HostState::new(main_thread_proxy),
+        move || exit_proxy.exit(),
+    )
+    .await?;
+
+    match main_thread_loop.run() {
+        EventLoopExit::WindowsClosed => {
+            log::info!("All windows were closed, stopping the guest");
+            Ok(())
+        }
+        EventLoopExit::Requested => guest.await?,
+    }
+}
 
+// Instantiates the example and runs it in the background, calling `on_return` once it returns.
+async fn start_guest<S: MainThreadSpawner>(
+    example: &str,
+    host_state: HostState<S>,
+    on_return: impl FnOnce() + Send + 'static,
+) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
     let mut config = Config::default();
     config.wasm_component_model(true);
     config.wasm_component_model_async(true);
     let engine = Engine::new(&config)?;
-    let mut linker: Linker<WorkloadState> = Linker::new(&engine);
+    let mut linker: Linker<WorkloadState<S>> = Linker::new(&engine);
 
     wasi_webgpu_wasmtime::add_to_linker(&mut linker)?;
     frame_buffer_wasmtime::add_to_linker(&mut linker)?;
     surface_wasmtime::add_all_to_linker(&mut linker)?;
-    Example::add_to_linker_imports::<_, WorkloadState>(&mut linker, |x| x)?;
+    Example::add_to_linker_imports::<_, WorkloadState<S>>(&mut linker, |x| x)?;
 
     let workload_state = host_state.add_workload();
 
     let mut store = Store::new(&engine, workload_state);
 
-    let wasm_path = format!("./target/example-{}.wasm", args.example);
+    let wasm_path = format!("./target/example-{example}.wasm");
 
     let component =
         Component::from_file(&engine, &wasm_path).context("Component file not found")?;
@@ -161,11 +229,9 @@ async fn main() -> anyhow::Result<()> {
         .await
         .unwrap();
 
-    tokio::spawn(async move {
-        instance.func_start().call_async(store, ()).await.unwrap();
-    });
-
-    main_thread_loop.run();
-
-    Ok(())
+    Ok(tokio::spawn(async move {
+        let result = instance.func_start().call_async(store, ()).await;
+        on_return();
+        anyhow::Ok(result.context("Guest failed")?)
+    }))
 }
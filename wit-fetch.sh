@@ -6,3 +6,10 @@ wkg wit fetch --config ./wkg-config.toml -d crates/wasi-webgpu-wasmtime/wit/
 wkg wit fetch --config ./wkg-config.toml -d crates/frame-buffer-wasmtime/wit/
 wkg wit fetch --config ./wkg-config.toml -d crates/surface-wasmtime/wit/
 wkg wit fetch --config ./wkg-config.toml -d examples/wit/
+
+# wasi-gfx:surface-ext isn't published. wkg resolves it through the override in wkg.toml,
+# and it's vendored from wit/surface-ext so that deps/ only ever holds unmodified packages.
+for dir in crates/surface-wasmtime/wit examples/wit; do
+    mkdir -p "$dir/deps/wasi-gfx-surface-ext-0.1.0"
+    cp wit/surface-ext/package.wit "$dir/deps/wasi-gfx-surface-ext-0.1.0/"
+done